@@ -0,0 +1,51 @@
+// Compares the contiguous, tiled `Matrix::multiply` against the previous
+// `Vec<Vec<_>>` triple loop. Run with `cargo bench --bench matrix_multiply`.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use project::matrix::Matrix;
+
+// The original row-of-rows layout and i-j-k loop, with the inner bound fixed so
+// both versions compute the same product.
+fn nested_multiply(a: &[Vec<f64>], b: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let mut result = vec![vec![0.0; b[0].len()]; a.len()];
+    for i in 0..a.len() {
+        for j in 0..b[0].len() {
+            let mut sum = 0.0;
+            for k in 0..b.len() {
+                sum += a[i][k] * b[k][j];
+            }
+            result[i][j] = sum;
+        }
+    }
+    result
+}
+
+fn inputs(size: usize) -> Vec<Vec<f64>> {
+    (0..size)
+        .map(|i| (0..size).map(|j| ((i * 31 + j * 17) % 101) as f64 / 101.0).collect())
+        .collect()
+}
+
+fn time<R>(iterations: u32, mut f: impl FnMut() -> R) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(f());
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    for (size, iterations) in [(512, 3), (2048, 1)] {
+        let rows = inputs(size);
+        let matrix = Matrix::from_rows(rows.clone()).unwrap();
+
+        let tiled = time(iterations, || matrix.multiply(&matrix).unwrap());
+        let nested = time(iterations, || nested_multiply(&rows, &rows));
+
+        println!("{0}×{0}", size);
+        println!("  Vec<Vec<f64>> i-j-k   {:>12?}", nested);
+        println!("  Matrix (tiled)        {:>12?}", tiled);
+        println!("  speed-up              {:>11.1}x", nested.as_secs_f64() / tiled.as_secs_f64());
+    }
+}
@@ -0,0 +1,69 @@
+// Compares the sorted `MathSet` against the previous `Vec::contains` implementation.
+// Run with `cargo bench --bench set_operations`.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use project::math_set::MathSet;
+
+// The original Vec-backed operations, kept here only as a baseline.
+fn vec_union(a: &[i64], b: &[i64]) -> Vec<i64> {
+    let mut union = a.to_vec();
+    for element in b {
+        if !union.contains(element) {
+            union.push(*element);
+        }
+    }
+    union
+}
+
+fn vec_intersection(a: &[i64], b: &[i64]) -> Vec<i64> {
+    a.iter().filter(|element| b.contains(element)).copied().collect()
+}
+
+fn vec_difference(a: &[i64], b: &[i64]) -> Vec<i64> {
+    a.iter().filter(|element| !b.contains(element)).copied().collect()
+}
+
+// Two overlapping pseudo-random inputs of the given size
+fn inputs(size: usize) -> (Vec<i64>, Vec<i64>) {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % (size as u64 * 2)) as i64
+    };
+    let a = (0..size).map(|_| next()).collect();
+    let b = (0..size).map(|_| next()).collect();
+    (a, b)
+}
+
+fn time<R>(mut f: impl FnMut() -> R) -> Duration {
+    let iterations = 5;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(f());
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    for size in [1_000, 10_000, 100_000, 1_000_000] {
+        let (a, b) = inputs(size);
+        let set_a = MathSet::new(a.clone());
+        let set_b = MathSet::new(b.clone());
+
+        println!("n = {}", size);
+        println!("  construction   MathSet {:>12?}", time(|| MathSet::new(a.clone())));
+        println!("  union          MathSet {:>12?}", time(|| set_a.union(&set_b)));
+        println!("  intersection   MathSet {:>12?}", time(|| set_a.intersection(&set_b)));
+        println!("  difference     MathSet {:>12?}", time(|| set_a.difference(&set_b)));
+
+        // The quadratic baseline takes minutes beyond this size.
+        if size <= 10_000 {
+            println!("  union          Vec     {:>12?}", time(|| vec_union(&a, &b)));
+            println!("  intersection   Vec     {:>12?}", time(|| vec_intersection(&a, &b)));
+            println!("  difference     Vec     {:>12?}", time(|| vec_difference(&a, &b)));
+        }
+    }
+}
@@ -4,67 +4,395 @@ pub mod math_set {
     use std::fs::File;
     use std::path::Path;
     use std::io::BufReader;
+    use std::str::FromStr;
+    use std::cmp::Ordering;
 
-    #[derive(Debug, Clone, PartialEq)]
-    pub struct MathSet {
-        pub elements: Vec<i32>,
+    // A finite set of elements of any type that can be compared for equality and
+    // ordering, so sets of sets (power sets, partitions) are themselves `MathSet`s.
+    //
+    // Elements are kept sorted and free of duplicates, which gives content-based
+    // equality and lets every operation run as a linear merge.
+    #[derive(Debug, Clone, PartialEq, PartialOrd)]
+    pub struct MathSet<T> {
+        elements: Vec<T>,
+    }
+
+    // Ordering used for storage; values that do not compare to themselves (NaN)
+    // are rejected before they get here.
+    pub(crate) fn compare<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
     }
 
-    impl MathSet {
-        pub fn new(elements: Vec<i32>) -> MathSet {
+    impl<T: PartialOrd + Clone> MathSet<T> {
+        // Sorts and deduplicates `elements`. Elements that do not compare equal
+        // to themselves (NaN) have no place in the order and are dropped.
+        pub fn new(mut elements: Vec<T>) -> MathSet<T> {
+            elements.retain(|element| element.partial_cmp(element).is_some());
+            elements.sort_by(compare);
+            elements.dedup_by(|a, b| compare(a, b) == Ordering::Equal);
             MathSet { elements }
         }
 
-        pub fn union(&self, other: &MathSet) -> MathSet {
-            let mut union = self.elements.clone();
+        pub fn union(&self, other: &MathSet<T>) -> MathSet<T> {
+            let mut union = Vec::with_capacity(self.elements.len() + other.elements.len());
+            let (mut i, mut j) = (0, 0);
 
-            for element in &other.elements {
-                if !union.contains(element) {
-                    union.push(*element);
+            while i < self.elements.len() && j < other.elements.len() {
+                match compare(&self.elements[i], &other.elements[j]) {
+                    Ordering::Less => {
+                        union.push(self.elements[i].clone());
+                        i += 1;
+                    }
+                    Ordering::Greater => {
+                        union.push(other.elements[j].clone());
+                        j += 1;
+                    }
+                    Ordering::Equal => {
+                        union.push(self.elements[i].clone());
+                        i += 1;
+                        j += 1;
+                    }
                 }
             }
+            union.extend_from_slice(&self.elements[i..]);
+            union.extend_from_slice(&other.elements[j..]);
 
             MathSet { elements: union }
         }
 
-        pub fn intersection(&self, other: &MathSet) -> MathSet {
+        pub fn intersection(&self, other: &MathSet<T>) -> MathSet<T> {
             let mut intersect = Vec::new();
+            let (mut i, mut j) = (0, 0);
 
-            for element in &self.elements {
-                if other.elements.contains(element) {
-                    intersect.push(*element);
+            while i < self.elements.len() && j < other.elements.len() {
+                match compare(&self.elements[i], &other.elements[j]) {
+                    Ordering::Less => i += 1,
+                    Ordering::Greater => j += 1,
+                    Ordering::Equal => {
+                        intersect.push(self.elements[i].clone());
+                        i += 1;
+                        j += 1;
+                    }
                 }
             }
 
             MathSet { elements: intersect }
         }
 
-        pub fn difference(&self, other: &MathSet) -> MathSet {
+        pub fn difference(&self, other: &MathSet<T>) -> MathSet<T> {
             let mut difference = Vec::new();
+            let (mut i, mut j) = (0, 0);
 
-            for element in &self.elements {
-                if !other.elements.contains(element) {
-                    difference.push(*element);
+            while i < self.elements.len() && j < other.elements.len() {
+                match compare(&self.elements[i], &other.elements[j]) {
+                    Ordering::Less => {
+                        difference.push(self.elements[i].clone());
+                        i += 1;
+                    }
+                    Ordering::Greater => j += 1,
+                    Ordering::Equal => {
+                        i += 1;
+                        j += 1;
+                    }
                 }
             }
+            difference.extend_from_slice(&self.elements[i..]);
 
             MathSet { elements: difference }
         }
-    }    
 
-    pub fn read_sets_from_csv(filename: &str) -> Result<Vec<MathSet>, Box<dyn Error>> {
+        pub fn symmetric_difference(&self, other: &MathSet<T>) -> MathSet<T> {
+            let mut symmetric = Vec::new();
+            let (mut i, mut j) = (0, 0);
+
+            while i < self.elements.len() && j < other.elements.len() {
+                match compare(&self.elements[i], &other.elements[j]) {
+                    Ordering::Less => {
+                        symmetric.push(self.elements[i].clone());
+                        i += 1;
+                    }
+                    Ordering::Greater => {
+                        symmetric.push(other.elements[j].clone());
+                        j += 1;
+                    }
+                    Ordering::Equal => {
+                        i += 1;
+                        j += 1;
+                    }
+                }
+            }
+            symmetric.extend_from_slice(&self.elements[i..]);
+            symmetric.extend_from_slice(&other.elements[j..]);
+
+            MathSet { elements: symmetric }
+        }
+
+        // Elements of `universe` that are not in this set
+        pub fn complement(&self, universe: &MathSet<T>) -> MathSet<T> {
+            universe.difference(self)
+        }
+
+        pub fn is_subset(&self, other: &MathSet<T>) -> bool {
+            if self.elements.len() > other.elements.len() {
+                return false;
+            }
+
+            let mut j = 0;
+            for element in &self.elements {
+                while j < other.elements.len() && compare(&other.elements[j], element) == Ordering::Less {
+                    j += 1;
+                }
+                if j == other.elements.len() || compare(&other.elements[j], element) != Ordering::Equal {
+                    return false;
+                }
+                j += 1;
+            }
+
+            true
+        }
+
+        pub fn is_proper_subset(&self, other: &MathSet<T>) -> bool {
+            self.elements.len() < other.elements.len() && self.is_subset(other)
+        }
+
+        pub fn is_superset(&self, other: &MathSet<T>) -> bool {
+            other.is_subset(self)
+        }
+
+        pub fn is_disjoint(&self, other: &MathSet<T>) -> bool {
+            let (mut i, mut j) = (0, 0);
+
+            while i < self.elements.len() && j < other.elements.len() {
+                match compare(&self.elements[i], &other.elements[j]) {
+                    Ordering::Less => i += 1,
+                    Ordering::Greater => j += 1,
+                    Ordering::Equal => return false,
+                }
+            }
+
+            true
+        }
+
+        pub fn contains(&self, element: &T) -> bool {
+            self.position(element).is_ok()
+        }
+
+        // Adds `element`, returning false if it was already present. An element
+        // that does not compare equal to itself (NaN) is not stored and also
+        // yields false; use `try_insert` to tell the two cases apart.
+        pub fn insert(&mut self, element: T) -> bool {
+            self.try_insert(element).unwrap_or(false)
+        }
+
+        // Like `insert`, but rejects elements that do not compare equal to
+        // themselves (NaN) with an error
+        pub fn try_insert(&mut self, element: T) -> Result<bool, &'static str> {
+            if element.partial_cmp(&element).is_none() {
+                return Err("Elements that are not equal to themselves (such as NaN) cannot be stored in a set.");
+            }
+
+            match self.position(&element) {
+                Ok(_) => Ok(false),
+                Err(index) => {
+                    self.elements.insert(index, element);
+                    Ok(true)
+                }
+            }
+        }
+
+        // Removes `element`, returning false if it was not present
+        pub fn remove(&mut self, element: &T) -> bool {
+            match self.position(element) {
+                Ok(index) => {
+                    self.elements.remove(index);
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+
+        pub fn cardinality(&self) -> usize {
+            self.elements.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.elements.is_empty()
+        }
+
+        // Position of `element` in ascending order, if present
+        pub fn index_of(&self, element: &T) -> Option<usize> {
+            self.position(element).ok()
+        }
+
+        fn position(&self, element: &T) -> Result<usize, usize> {
+            self.elements.binary_search_by(|probe| compare(probe, element))
+        }
+    }
+
+    impl<T> MathSet<T> {
+        // Wraps elements that are already sorted and distinct
+        pub(crate) fn from_sorted(elements: Vec<T>) -> MathSet<T> {
+            MathSet { elements }
+        }
+
+        // Elements in ascending order
+        pub fn elements(&self) -> &[T] {
+            &self.elements
+        }
+
+        pub fn iter(&self) -> std::slice::Iter<'_, T> {
+            self.elements.iter()
+        }
+
+        // Lazily yields all 2^n subsets, starting with the empty set
+        pub fn power_set(&self) -> PowerSet<'_, T> {
+            PowerSet { elements: &self.elements, chosen: vec![false; self.elements.len()], done: false }
+        }
+
+        // Lazily yields every ordered pair (a, b) with a in self and b in other
+        pub fn cartesian_product<'a, U>(&'a self, other: &'a MathSet<U>) -> CartesianProduct<'a, T, U> {
+            CartesianProduct { left: &self.elements, right: &other.elements, i: 0, j: 0 }
+        }
+    }
+
+    impl<T: PartialOrd + Clone> FromIterator<T> for MathSet<T> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> MathSet<T> {
+            MathSet::new(iter.into_iter().collect())
+        }
+    }
+
+    impl<'a, T> IntoIterator for &'a MathSet<T> {
+        type Item = &'a T;
+        type IntoIter = std::slice::Iter<'a, T>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.elements.iter()
+        }
+    }
+
+    pub struct PowerSet<'a, T> {
+        elements: &'a [T],
+        chosen: Vec<bool>,
+        done: bool,
+    }
+
+    impl<T: Clone> Iterator for PowerSet<'_, T> {
+        type Item = MathSet<T>;
+
+        fn next(&mut self) -> Option<MathSet<T>> {
+            if self.done {
+                return None;
+            }
+
+            // Picking in index order keeps the subset sorted
+            let subset = self
+                .elements
+                .iter()
+                .zip(&self.chosen)
+                .filter(|(_, &chosen)| chosen)
+                .map(|(element, _)| element.clone())
+                .collect();
+
+            // Advance the binary counter; wrapping back to all-false means we are done
+            self.done = true;
+            for chosen in self.chosen.iter_mut() {
+                *chosen = !*chosen;
+                if *chosen {
+                    self.done = false;
+                    break;
+                }
+            }
+
+            Some(MathSet { elements: subset })
+        }
+    }
+
+    pub struct CartesianProduct<'a, T, U> {
+        left: &'a [T],
+        right: &'a [U],
+        i: usize,
+        j: usize,
+    }
+
+    impl<T: Clone, U: Clone> Iterator for CartesianProduct<'_, T, U> {
+        type Item = (T, U);
+
+        fn next(&mut self) -> Option<(T, U)> {
+            if self.i >= self.left.len() || self.right.is_empty() {
+                return None;
+            }
+
+            let pair = (self.left[self.i].clone(), self.right[self.j].clone());
+            self.j += 1;
+            if self.j == self.right.len() {
+                self.j = 0;
+                self.i += 1;
+            }
+
+            Some(pair)
+        }
+    }
+
+    // Lazily yields every tuple of the n-ary product S1 x S2 x ... x Sn, in
+    // lexicographic order. The product of no sets is the single empty tuple.
+    pub fn product<T>(sets: &[MathSet<T>]) -> Product<'_, T> {
+        let done = sets.iter().any(|set| set.elements.is_empty());
+        Product { sets, indices: vec![0; sets.len()], done }
+    }
+
+    pub struct Product<'a, T> {
+        sets: &'a [MathSet<T>],
+        indices: Vec<usize>,
+        done: bool,
+    }
+
+    impl<T: Clone> Iterator for Product<'_, T> {
+        type Item = Vec<T>;
+
+        fn next(&mut self) -> Option<Vec<T>> {
+            if self.done {
+                return None;
+            }
+
+            let tuple = self
+                .sets
+                .iter()
+                .zip(&self.indices)
+                .map(|(set, &index)| set.elements[index].clone())
+                .collect();
+
+            // Odometer-style increment from the last position
+            self.done = true;
+            for position in (0..self.indices.len()).rev() {
+                self.indices[position] += 1;
+                if self.indices[position] < self.sets[position].elements.len() {
+                    self.done = false;
+                    break;
+                }
+                self.indices[position] = 0;
+            }
+
+            Some(tuple)
+        }
+    }
+
+    pub fn read_sets_from_csv<T>(filename: &str) -> Result<Vec<MathSet<T>>, Box<dyn Error>>
+    where
+        T: FromStr + PartialOrd + Clone,
+        T::Err: Error + 'static,
+    {
         let path = Path::new(filename);
-        let file = File::open(&path)?;
+        let file = File::open(path)?;
         let reader = BufReader::new(file);
 
         let mut sets = Vec::new();
 
         for line in reader.lines() {
             let line = line?;
-            let elements: Vec<i32> = line
+            let elements = line
                 .split(',')
-                .map(|s| s.trim().parse::<i32>().unwrap())
-                .collect();
+                .map(|s| s.trim().parse::<T>())
+                .collect::<Result<Vec<T>, _>>()?;
             let math_set = MathSet::new(elements);
             sets.push(math_set);
         }
@@ -75,178 +403,5358 @@ pub mod math_set {
     #[test]
     fn test_set_operations() {
         // Define some example sets
-        let set1 = MathSet { elements: vec![1, 2, 3] };
-        let set2 = MathSet { elements: vec![3, 4, 5] };
+        let set1 = MathSet::new(vec![1, 2, 3]);
+        let set2 = MathSet::new(vec![3, 4, 5]);
 
         // Test union
         let union_result = set1.union(&set2);
-        let expected_union = MathSet { elements: vec![1, 2, 3, 4, 5] };
+        let expected_union = MathSet::new(vec![1, 2, 3, 4, 5]);
         assert_eq!(union_result, expected_union);
 
         // Test intersection
         let intersection_result = set1.intersection(&set2);
-        let expected_intersection = MathSet { elements: vec![3] };
+        let expected_intersection = MathSet::new(vec![3]);
         assert_eq!(intersection_result, expected_intersection);
 
-        // Test difference
-        let difference_result = set1.difference(&set2);
-        let expected_difference = MathSet { elements: vec![1, 2] };
-        assert_eq!(difference_result, expected_difference);
-    }
-}
+        // Test difference
+        let difference_result = set1.difference(&set2);
+        let expected_difference = MathSet::new(vec![1, 2]);
+        assert_eq!(difference_result, expected_difference);
+
+        // Test symmetric difference and complement
+        assert_eq!(set1.symmetric_difference(&set2), MathSet::new(vec![1, 2, 4, 5]));
+        let universe = MathSet::new((0..=6).collect());
+        assert_eq!(set1.complement(&universe), MathSet::new(vec![0, 4, 5, 6]));
+
+        // Test relations
+        let small = MathSet::new(vec![1, 3]);
+        assert!(small.is_subset(&set1));
+        assert!(small.is_proper_subset(&set1));
+        assert!(set1.is_subset(&set1));
+        assert!(!set1.is_proper_subset(&set1));
+        assert!(set1.is_superset(&small));
+        assert!(!set1.is_subset(&set2));
+        assert!(!set1.is_disjoint(&set2));
+        assert!(small.is_disjoint(&MathSet::new(vec![2, 4])));
+
+        // Test membership and mutation
+        let mut set = MathSet::new(vec![5, 1]);
+        assert!(set.contains(&5));
+        assert!(!set.contains(&3));
+        assert!(set.insert(3));
+        assert!(!set.insert(3));
+        assert!(set.remove(&1));
+        assert!(!set.remove(&1));
+        assert_eq!(set.elements(), &[3, 5]);
+        assert_eq!(set.cardinality(), 2);
+
+        // Test power set
+        let subsets: MathSet<MathSet<i32>> = set1.power_set().collect();
+        assert_eq!(subsets.cardinality(), 8);
+        assert!(subsets.contains(&MathSet::new(vec![])));
+        assert!(subsets.contains(&MathSet::new(vec![1, 3])));
+        assert!(subsets.contains(&set1));
+        assert_eq!(MathSet::new((0..30).collect()).power_set().nth(5), Some(MathSet::new(vec![0, 2])));
+
+        // Test Cartesian and n-ary products
+        let letters = MathSet::new(vec!['a', 'b']);
+        let pairs: Vec<(i32, char)> = set1.cartesian_product(&letters).collect();
+        assert_eq!(pairs.len(), 6);
+        assert_eq!(pairs[0], (1, 'a'));
+        assert_eq!(pairs[5], (3, 'b'));
+
+        let tuples: Vec<Vec<i32>> = product(&[MathSet::new(vec![0, 1]), MathSet::new(vec![5]), MathSet::new(vec![7, 8])]).collect();
+        assert_eq!(tuples, vec![vec![0, 5, 7], vec![0, 5, 8], vec![1, 5, 7], vec![1, 5, 8]]);
+        assert_eq!(product::<i32>(&[]).count(), 1);
+        assert_eq!(product(&[set1.clone(), MathSet::new(vec![])]).count(), 0);
+
+        // Test duplicates and ordering
+        assert_eq!(MathSet::new(vec![1, 1, 2]), MathSet::new(vec![2, 1]));
+        assert_eq!(MathSet::new(vec![3, 1, 3, 2]).elements(), &[1, 2, 3]);
+        assert_eq!(MathSet::new(vec![f64::NAN, 1.0]).elements(), &[1.0]);
+        let mut floats = MathSet::new(vec![1.0]);
+        assert!(!floats.insert(f64::NAN));
+        assert!(floats.try_insert(f64::NAN).is_err());
+        assert_eq!(floats.try_insert(1.0), Ok(false));
+        assert_eq!(floats.try_insert(2.0), Ok(true));
+        assert_eq!(floats.elements(), &[1.0, 2.0]);
+
+        // Test non-integer elements
+        let words = MathSet::new(vec!["a".to_string(), "b".to_string()]);
+        let more_words = MathSet::new(vec!["b".to_string(), "c".to_string()]);
+        assert_eq!(words.intersection(&more_words), MathSet::new(vec!["b".to_string()]));
+
+        let floats = MathSet::new(vec![0.5, 1.5]);
+        assert_eq!(floats.difference(&MathSet::new(vec![1.5])), MathSet::new(vec![0.5]));
+
+        // Test sets of points
+        use crate::vector::Vector;
+        let points = MathSet::new(vec![Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0), Vector::new(1.0, 0.0, 0.0)]);
+        let more_points = MathSet::new(vec![Vector::new(0.0, 1.0, 0.0), Vector::new(0.0, 0.0, 1.0)]);
+        assert_eq!(points.cardinality(), 2);
+        assert_eq!(points.intersection(&more_points), MathSet::new(vec![Vector::new(0.0, 1.0, 0.0)]));
+        assert_eq!(points.union(&more_points).cardinality(), 3);
+
+        // Test sets of sets
+        let family = MathSet::new(vec![set1.clone(), set2.clone(), set1.clone()]);
+        let other_family = MathSet::new(vec![set2.clone()]);
+        assert_eq!(family.difference(&other_family), MathSet::new(vec![set1]));
+    }
+}
+
+pub mod set_expr {
+    use std::error::Error;
+    use std::fmt;
+    use std::iter::Peekable;
+    use std::iter::Enumerate;
+    use std::str::Chars;
+
+    use crate::math_set::MathSet;
+
+    // Parsed set-algebra expression. Named sets A, B, C... refer to rows of the
+    // set list by position.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Expr {
+        Set(char),
+        Empty,
+        Union(Box<Expr>, Box<Expr>),
+        Intersection(Box<Expr>, Box<Expr>),
+        Difference(Box<Expr>, Box<Expr>),
+        SymmetricDifference(Box<Expr>, Box<Expr>),
+        Complement(Box<Expr>),
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum ExprError {
+        UnexpectedChar { position: usize, found: char },
+        UnexpectedEnd { expected: &'static str },
+        UnknownSet { name: char, available: usize },
+    }
+
+    impl fmt::Display for ExprError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ExprError::UnexpectedChar { position, found } => {
+                    write!(f, "unexpected '{}' at position {}", found, position)
+                }
+                ExprError::UnexpectedEnd { expected } => {
+                    write!(f, "expression ended early, expected {}", expected)
+                }
+                ExprError::UnknownSet { name, available } => match available {
+                    0 => write!(f, "unknown set '{}': no sets are loaded", name),
+                    _ => write!(f, "unknown set '{}': only A to {} are loaded", name, set_name((*available).min(26) - 1)),
+                },
+            }
+        }
+    }
+
+    impl Error for ExprError {}
+
+    fn set_name(index: usize) -> char {
+        (b'A' + index as u8) as char
+    }
+
+    impl fmt::Display for Expr {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Expr::Set(name) => write!(f, "{}", name),
+                Expr::Empty => write!(f, "∅"),
+                Expr::Union(a, b) => write!(f, "({} ∪ {})", a, b),
+                Expr::Intersection(a, b) => write!(f, "({} ∩ {})", a, b),
+                Expr::Difference(a, b) => write!(f, "({} \\ {})", a, b),
+                Expr::SymmetricDifference(a, b) => write!(f, "({} Δ {})", a, b),
+                Expr::Complement(a) => write!(f, "{}ᶜ", a),
+            }
+        }
+    }
+
+    impl Expr {
+        // Evaluates against `sets` (A is `sets[0]`); complements are taken in `universe`
+        pub fn evaluate<T: PartialOrd + Clone>(&self, sets: &[MathSet<T>], universe: &MathSet<T>) -> Result<MathSet<T>, ExprError> {
+            Ok(match self {
+                Expr::Set(name) => {
+                    // Only 'A'..='Z' name a set; anything else built by hand is unknown
+                    let index = u8::try_from(*name).ok().and_then(|c| c.checked_sub(b'A')).filter(|&i| i < 26);
+                    match index.and_then(|i| sets.get(i as usize)) {
+                        Some(set) => set.clone(),
+                        None => return Err(ExprError::UnknownSet { name: *name, available: sets.len() }),
+                    }
+                }
+                Expr::Empty => MathSet::new(Vec::new()),
+                Expr::Union(a, b) => a.evaluate(sets, universe)?.union(&b.evaluate(sets, universe)?),
+                Expr::Intersection(a, b) => a.evaluate(sets, universe)?.intersection(&b.evaluate(sets, universe)?),
+                Expr::Difference(a, b) => a.evaluate(sets, universe)?.difference(&b.evaluate(sets, universe)?),
+                Expr::SymmetricDifference(a, b) => a.evaluate(sets, universe)?.symmetric_difference(&b.evaluate(sets, universe)?),
+                Expr::Complement(a) => a.evaluate(sets, universe)?.complement(universe),
+            })
+        }
+    }
+
+    // Operator precedence, loosest first:
+    //   ∪ | +   \ -   Δ ^     union, difference, symmetric difference (left-assoc)
+    //   ∩ &                   intersection
+    //   ~ ¬ !  prefix, ' ᶜ postfix   complement
+    pub fn parse(input: &str) -> Result<Expr, ExprError> {
+        let mut parser = Parser { chars: input.chars().enumerate().peekable() };
+        let expr = parser.expression()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some((position, found)) => Err(ExprError::UnexpectedChar { position, found }),
+        }
+    }
+
+    // Parses and evaluates `input` in one step
+    pub fn evaluate<T: PartialOrd + Clone>(input: &str, sets: &[MathSet<T>], universe: &MathSet<T>) -> Result<MathSet<T>, ExprError> {
+        parse(input)?.evaluate(sets, universe)
+    }
+
+    struct Parser<'a> {
+        chars: Peekable<Enumerate<Chars<'a>>>,
+    }
+
+    impl Parser<'_> {
+        fn peek(&mut self) -> Option<(usize, char)> {
+            while let Some(&(_, c)) = self.chars.peek() {
+                if !c.is_whitespace() {
+                    break;
+                }
+                self.chars.next();
+            }
+            self.chars.peek().copied()
+        }
+
+        fn expression(&mut self) -> Result<Expr, ExprError> {
+            let mut left = self.term()?;
+
+            while let Some((_, c)) = self.peek() {
+                let combine: fn(Box<Expr>, Box<Expr>) -> Expr = match c {
+                    '∪' | '|' | '+' => Expr::Union,
+                    '\\' | '-' => Expr::Difference,
+                    'Δ' | '^' => Expr::SymmetricDifference,
+                    _ => break,
+                };
+                self.chars.next();
+                let right = self.term()?;
+                left = combine(Box::new(left), Box::new(right));
+            }
+
+            Ok(left)
+        }
+
+        fn term(&mut self) -> Result<Expr, ExprError> {
+            let mut left = self.factor()?;
+
+            while let Some((_, '∩' | '&')) = self.peek() {
+                self.chars.next();
+                let right = self.factor()?;
+                left = Expr::Intersection(Box::new(left), Box::new(right));
+            }
+
+            Ok(left)
+        }
+
+        fn factor(&mut self) -> Result<Expr, ExprError> {
+            if let Some((_, '~' | '¬' | '!')) = self.peek() {
+                self.chars.next();
+                return Ok(Expr::Complement(Box::new(self.factor()?)));
+            }
+
+            let mut expr = self.primary()?;
+            while let Some((_, '\'' | 'ᶜ' | '′')) = self.peek() {
+                self.chars.next();
+                expr = Expr::Complement(Box::new(expr));
+            }
+
+            Ok(expr)
+        }
+
+        fn primary(&mut self) -> Result<Expr, ExprError> {
+            match self.peek() {
+                None => Err(ExprError::UnexpectedEnd { expected: "a set name or '('" }),
+                Some((_, c)) if c.is_ascii_uppercase() => {
+                    self.chars.next();
+                    Ok(Expr::Set(c))
+                }
+                Some((_, '∅')) => {
+                    self.chars.next();
+                    Ok(Expr::Empty)
+                }
+                Some((_, '{')) => {
+                    self.chars.next();
+                    self.expect('}', "'}'")?;
+                    Ok(Expr::Empty)
+                }
+                Some((_, '(')) => {
+                    self.chars.next();
+                    let expr = self.expression()?;
+                    self.expect(')', "')'")?;
+                    Ok(expr)
+                }
+                Some((position, found)) => Err(ExprError::UnexpectedChar { position, found }),
+            }
+        }
+
+        fn expect(&mut self, wanted: char, expected: &'static str) -> Result<(), ExprError> {
+            match self.peek() {
+                Some((_, c)) if c == wanted => {
+                    self.chars.next();
+                    Ok(())
+                }
+                Some((position, found)) => Err(ExprError::UnexpectedChar { position, found }),
+                None => Err(ExprError::UnexpectedEnd { expected }),
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_expressions() {
+        let sets = vec![
+            MathSet::new(vec![1, 2, 3]),
+            MathSet::new(vec![3, 4]),
+            MathSet::new(vec![2, 3, 5]),
+            MathSet::new(vec![3, 5, 6]),
+        ];
+        let universe = MathSet::new((1..=6).collect());
+
+        // Test operators and their ASCII equivalents
+        assert_eq!(evaluate("(A ∪ B) \\ (C ∩ D)", &sets, &universe), Ok(MathSet::new(vec![1, 2, 4])));
+        assert_eq!(evaluate("(A | B) - (C & D)", &sets, &universe), Ok(MathSet::new(vec![1, 2, 4])));
+        assert_eq!(evaluate("A Δ B", &sets, &universe), evaluate("A ^ B", &sets, &universe));
+        assert_eq!(evaluate("A'", &sets, &universe), Ok(MathSet::new(vec![4, 5, 6])));
+        assert_eq!(evaluate("~A", &sets, &universe), evaluate("Aᶜ", &sets, &universe));
+        assert_eq!(evaluate("A ∩ ∅", &sets, &universe), Ok(MathSet::new(vec![])));
+
+        // Test precedence: intersection binds tighter than union
+        assert_eq!(evaluate("A ∪ B ∩ C", &sets, &universe), evaluate("A ∪ (B ∩ C)", &sets, &universe));
+        assert_eq!(parse("A - B - C"), parse("(A - B) - C"));
+        assert_eq!(parse("~A & B").unwrap().to_string(), "(Aᶜ ∩ B)");
+
+        // Test errors
+        assert_eq!(parse("A ∪"), Err(ExprError::UnexpectedEnd { expected: "a set name or '('" }));
+        assert_eq!(parse("(A ∪ B"), Err(ExprError::UnexpectedEnd { expected: "')'" }));
+        assert_eq!(parse("A ? B"), Err(ExprError::UnexpectedChar { position: 2, found: '?' }));
+        assert_eq!(parse("A B"), Err(ExprError::UnexpectedChar { position: 2, found: 'B' }));
+        assert_eq!(parse("A ∪ ∩"), Err(ExprError::UnexpectedChar { position: 4, found: '∩' }));
+        assert_eq!(evaluate("A ∪ Z", &sets, &universe), Err(ExprError::UnknownSet { name: 'Z', available: 4 }));
+        for name in ['a', '0', '[', 'Ł'] {
+            assert_eq!(Expr::Set(name).evaluate(&sets, &universe), Err(ExprError::UnknownSet { name, available: 4 }));
+        }
+        assert_eq!(
+            ExprError::UnknownSet { name: 'Z', available: 4 }.to_string(),
+            "unknown set 'Z': only A to D are loaded"
+        );
+    }
+}
+
+pub mod relation {
+    use crate::math_set::MathSet;
+
+    // A binary relation R ⊆ A × B, stored as a set of ordered pairs together with
+    // the sets it relates.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Relation<A, B> {
+        domain: MathSet<A>,
+        codomain: MathSet<B>,
+        pairs: MathSet<(A, B)>,
+    }
+
+    impl<A: PartialOrd + Clone, B: PartialOrd + Clone> Relation<A, B> {
+        pub fn new(domain: MathSet<A>, codomain: MathSet<B>, pairs: Vec<(A, B)>) -> Result<Relation<A, B>, &'static str> {
+            if pairs.iter().any(|(a, b)| !domain.contains(a) || !codomain.contains(b)) {
+                return Err("Every pair of a relation must lie in domain × codomain.");
+            }
+
+            Ok(Relation { domain, codomain, pairs: MathSet::new(pairs) })
+        }
+
+        pub fn domain(&self) -> &MathSet<A> {
+            &self.domain
+        }
+
+        pub fn codomain(&self) -> &MathSet<B> {
+            &self.codomain
+        }
+
+        pub fn pairs(&self) -> &MathSet<(A, B)> {
+            &self.pairs
+        }
+
+        pub fn contains(&self, a: &A, b: &B) -> bool {
+            self.pairs.contains(&(a.clone(), b.clone()))
+        }
+
+        // R⁻¹ = {(b, a) : (a, b) ∈ R}
+        pub fn inverse(&self) -> Relation<B, A> {
+            let pairs = self.pairs.iter().map(|(a, b)| (b.clone(), a.clone())).collect();
+            Relation { domain: self.codomain.clone(), codomain: self.domain.clone(), pairs }
+        }
+
+        // Applies `self` first and then `other`, i.e. other ∘ self
+        pub fn compose<C: PartialOrd + Clone>(&self, other: &Relation<B, C>) -> Relation<A, C> {
+            let others = other.pairs.elements();
+            let mut pairs = Vec::new();
+
+            for (a, b) in self.pairs.iter() {
+                // `other` is sorted by its first component, so the matches are contiguous
+                let start = others.partition_point(|(middle, _)| middle < b);
+                for (_, c) in others[start..].iter().take_while(|(middle, _)| middle == b) {
+                    pairs.push((a.clone(), c.clone()));
+                }
+            }
+
+            Relation { domain: self.domain.clone(), codomain: other.codomain.clone(), pairs: MathSet::new(pairs) }
+        }
+    }
+
+    // Relations on a single set, where the order-theoretic properties make sense.
+    // Properties are checked over the domain.
+    impl<T: PartialOrd + Clone> Relation<T, T> {
+        pub fn on(set: MathSet<T>, pairs: Vec<(T, T)>) -> Result<Relation<T, T>, &'static str> {
+            Relation::new(set.clone(), set, pairs)
+        }
+
+        pub fn is_reflexive(&self) -> bool {
+            self.domain.iter().all(|x| self.contains(x, x))
+        }
+
+        pub fn is_symmetric(&self) -> bool {
+            self.pairs.iter().all(|(a, b)| self.contains(b, a))
+        }
+
+        pub fn is_antisymmetric(&self) -> bool {
+            self.pairs.iter().all(|(a, b)| a == b || !self.contains(b, a))
+        }
+
+        pub fn is_transitive(&self) -> bool {
+            self.compose(self).pairs.is_subset(&self.pairs)
+        }
+
+        pub fn is_equivalence(&self) -> bool {
+            self.is_reflexive() && self.is_symmetric() && self.is_transitive()
+        }
+
+        pub fn is_partial_order(&self) -> bool {
+            self.is_reflexive() && self.is_antisymmetric() && self.is_transitive()
+        }
+
+        pub fn reflexive_closure(&self) -> Relation<T, T> {
+            let diagonal = self.domain.iter().map(|x| (x.clone(), x.clone())).collect();
+            self.with_pairs(self.pairs.union(&diagonal))
+        }
+
+        pub fn symmetric_closure(&self) -> Relation<T, T> {
+            self.with_pairs(self.pairs.union(&self.inverse().pairs))
+        }
+
+        // Warshall's algorithm over the union of domain and codomain
+        pub fn transitive_closure(&self) -> Relation<T, T> {
+            let carrier = self.domain.union(&self.codomain);
+            let n = carrier.cardinality();
+            let mut reach = vec![vec![false; n]; n];
+
+            for (a, b) in self.pairs.iter() {
+                if let (Some(i), Some(j)) = (carrier.index_of(a), carrier.index_of(b)) {
+                    reach[i][j] = true;
+                }
+            }
+
+            for k in 0..n {
+                let through = reach[k].clone();
+                for row in reach.iter_mut().filter(|row| row[k]) {
+                    for (cell, &reachable) in row.iter_mut().zip(&through) {
+                        *cell |= reachable;
+                    }
+                }
+            }
+
+            let elements = carrier.elements();
+            let mut pairs = Vec::new();
+            for (i, row) in reach.iter().enumerate() {
+                for (j, &reachable) in row.iter().enumerate() {
+                    if reachable {
+                        pairs.push((elements[i].clone(), elements[j].clone()));
+                    }
+                }
+            }
+
+            self.with_pairs(MathSet::new(pairs))
+        }
+
+        // The blocks of an equivalence relation, ordered by their smallest element
+        pub fn equivalence_classes(&self) -> Result<Vec<MathSet<T>>, &'static str> {
+            if !self.is_equivalence() {
+                return Err("Equivalence classes require a reflexive, symmetric and transitive relation.");
+            }
+
+            let mut classes: Vec<MathSet<T>> = Vec::new();
+            for x in self.domain.iter() {
+                if classes.iter().any(|class| class.contains(x)) {
+                    continue;
+                }
+                classes.push(self.related_to(x));
+            }
+
+            Ok(classes)
+        }
+
+        // Covering pairs (a, b) of a partial order: a < b with nothing strictly between
+        pub fn hasse_edges(&self) -> Result<Vec<(T, T)>, &'static str> {
+            if !self.is_partial_order() {
+                return Err("Hasse diagrams require a reflexive, antisymmetric and transitive relation.");
+            }
+
+            let edges = self
+                .pairs
+                .iter()
+                .filter(|(a, b)| a != b)
+                .filter(|(a, b)| {
+                    !self.domain.iter().any(|z| z != a && z != b && self.contains(a, z) && self.contains(z, b))
+                })
+                .cloned()
+                .collect();
+
+            Ok(edges)
+        }
+
+        // Elements with nothing strictly below them
+        pub fn minimal_elements(&self) -> MathSet<T> {
+            self.domain
+                .iter()
+                .filter(|x| !self.pairs.iter().any(|(a, b)| b == *x && a != *x))
+                .cloned()
+                .collect()
+        }
+
+        // Elements with nothing strictly above them
+        pub fn maximal_elements(&self) -> MathSet<T> {
+            self.domain
+                .iter()
+                .filter(|x| !self.pairs.iter().any(|(a, b)| a == *x && b != *x))
+                .cloned()
+                .collect()
+        }
+
+        fn related_to(&self, x: &T) -> MathSet<T> {
+            self.pairs.iter().filter(|(a, _)| a == x).map(|(_, b)| b.clone()).collect()
+        }
+
+        fn with_pairs(&self, pairs: MathSet<(T, T)>) -> Relation<T, T> {
+            Relation { domain: self.domain.clone(), codomain: self.codomain.clone(), pairs }
+        }
+    }
+
+    #[test]
+    fn test_relation_operations() {
+        let set = MathSet::new(vec![1, 2, 3, 4]);
+
+        // Test construction
+        assert!(Relation::on(set.clone(), vec![(1, 5)]).is_err());
+
+        // Test properties and closures
+        let less = Relation::on(set.clone(), vec![(1, 2), (2, 3), (3, 4)]).unwrap();
+        assert!(!less.is_reflexive());
+        assert!(!less.is_symmetric());
+        assert!(less.is_antisymmetric());
+        assert!(!less.is_transitive());
+
+        let closure = less.transitive_closure();
+        assert!(closure.is_transitive());
+        assert_eq!(closure.pairs().cardinality(), 6);
+        assert!(closure.contains(&1, &4));
+
+        let order = closure.reflexive_closure();
+        assert!(order.is_partial_order());
+        assert!(!order.is_equivalence());
+        assert!(less.symmetric_closure().is_symmetric());
+
+        // Test inverse and composition
+        assert!(less.inverse().contains(&2, &1));
+        let two_steps = less.compose(&less);
+        assert_eq!(two_steps.pairs(), &MathSet::new(vec![(1, 3), (2, 4)]));
+
+        let letters = Relation::new(set.clone(), MathSet::new(vec!['a', 'b']), vec![(2, 'a'), (3, 'b')]).unwrap();
+        assert_eq!(less.compose(&letters).pairs(), &MathSet::new(vec![(1, 'a'), (2, 'b')]));
+
+        // Test partial-order features
+        assert_eq!(order.hasse_edges(), Ok(vec![(1, 2), (2, 3), (3, 4)]));
+        assert_eq!(order.minimal_elements(), MathSet::new(vec![1]));
+        assert_eq!(order.maximal_elements(), MathSet::new(vec![4]));
+        assert!(less.hasse_edges().is_err());
+
+        let divides = Relation::on(
+            MathSet::new(vec![1, 2, 3, 6]),
+            vec![(1, 1), (2, 2), (3, 3), (6, 6), (1, 2), (1, 3), (1, 6), (2, 6), (3, 6)],
+        )
+        .unwrap();
+        assert_eq!(divides.hasse_edges(), Ok(vec![(1, 2), (1, 3), (2, 6), (3, 6)]));
+
+        // Test equivalence classes (same parity)
+        let parity_pairs = set
+            .cartesian_product(&set)
+            .filter(|(a, b)| (a - b) % 2 == 0)
+            .collect();
+        let parity = Relation::on(set.clone(), parity_pairs).unwrap();
+        assert!(parity.is_equivalence());
+        assert_eq!(
+            parity.equivalence_classes(),
+            Ok(vec![MathSet::new(vec![1, 3]), MathSet::new(vec![2, 4])])
+        );
+        assert!(order.equivalence_classes().is_err());
+    }
+}
+
+pub mod function {
+    use csv::ReaderBuilder;
+    use std::error::Error;
+    use std::str::FromStr;
+
+    use crate::math_set::MathSet;
+
+    // A total function f: domain → codomain, stored as its graph sorted by argument.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SetFunction<A, B> {
+        domain: MathSet<A>,
+        codomain: MathSet<B>,
+        mapping: MathSet<(A, B)>,
+    }
+
+    impl<A: PartialOrd + Clone, B: PartialOrd + Clone> SetFunction<A, B> {
+        // Checks that every domain element maps to exactly one codomain element
+        pub fn new(domain: MathSet<A>, codomain: MathSet<B>, mapping: Vec<(A, B)>) -> Result<SetFunction<A, B>, &'static str> {
+            let mapping = MathSet::new(mapping);
+
+            for (a, b) in mapping.iter() {
+                if !domain.contains(a) {
+                    return Err("Mapping contains an argument outside the domain.");
+                }
+                if !codomain.contains(b) {
+                    return Err("Mapping contains a value outside the codomain.");
+                }
+            }
+
+            let pairs = mapping.elements();
+            if pairs.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+                return Err("Mapping sends an element to more than one value.");
+            }
+            if pairs.len() != domain.cardinality() {
+                return Err("Mapping leaves some domain elements without a value.");
+            }
+
+            Ok(SetFunction { domain, codomain, mapping })
+        }
+
+        // Builds a function whose domain is exactly the arguments of `mapping`
+        pub fn from_mapping(mapping: Vec<(A, B)>, codomain: MathSet<B>) -> Result<SetFunction<A, B>, &'static str> {
+            let domain = mapping.iter().map(|(a, _)| a.clone()).collect();
+            SetFunction::new(domain, codomain, mapping)
+        }
+
+        pub fn domain(&self) -> &MathSet<A> {
+            &self.domain
+        }
+
+        pub fn codomain(&self) -> &MathSet<B> {
+            &self.codomain
+        }
+
+        pub fn apply(&self, a: &A) -> Option<&B> {
+            let pairs = self.mapping.elements();
+            let index = pairs.partition_point(|(argument, _)| argument < a);
+            pairs.get(index).filter(|(argument, _)| argument == a).map(|(_, b)| b)
+        }
+
+        // f(S) = {f(x) : x ∈ S}; elements of `subset` outside the domain are ignored
+        pub fn image(&self, subset: &MathSet<A>) -> MathSet<B> {
+            subset.iter().filter_map(|a| self.apply(a)).cloned().collect()
+        }
+
+        // f⁻¹(S) = {x : f(x) ∈ S}
+        pub fn preimage(&self, subset: &MathSet<B>) -> MathSet<A> {
+            self.mapping.iter().filter(|(_, b)| subset.contains(b)).map(|(a, _)| a.clone()).collect()
+        }
+
+        pub fn range(&self) -> MathSet<B> {
+            self.image(&self.domain)
+        }
+
+        pub fn is_injective(&self) -> bool {
+            self.range().cardinality() == self.domain.cardinality()
+        }
+
+        pub fn is_surjective(&self) -> bool {
+            self.range() == self.codomain
+        }
+
+        pub fn is_bijective(&self) -> bool {
+            self.is_injective() && self.is_surjective()
+        }
+
+        // Applies `self` first and then `other`, i.e. other ∘ self
+        pub fn compose<C: PartialOrd + Clone>(&self, other: &SetFunction<B, C>) -> Result<SetFunction<A, C>, &'static str> {
+            if !self.codomain.is_subset(other.domain()) {
+                return Err("The codomain of the first function must lie in the domain of the second.");
+            }
+
+            let mapping = self
+                .mapping
+                .iter()
+                .filter_map(|(a, b)| other.apply(b).map(|c| (a.clone(), c.clone())))
+                .collect();
+
+            Ok(SetFunction { domain: self.domain.clone(), codomain: other.codomain.clone(), mapping })
+        }
+
+        pub fn inverse(&self) -> Result<SetFunction<B, A>, &'static str> {
+            if !self.is_bijective() {
+                return Err("Only bijective functions have an inverse.");
+            }
+
+            let mapping = self.mapping.iter().map(|(a, b)| (b.clone(), a.clone())).collect();
+            Ok(SetFunction { domain: self.codomain.clone(), codomain: self.domain.clone(), mapping })
+        }
+    }
+
+    // Reads `argument,value` rows (after a header line) as a mapping
+    pub fn read_mapping_from_csv<A, B>(filename: &str) -> Result<Vec<(A, B)>, Box<dyn Error>>
+    where
+        A: FromStr,
+        B: FromStr,
+        A::Err: Error + 'static,
+        B::Err: Error + 'static,
+    {
+        let mut reader = ReaderBuilder::new().has_headers(true).from_path(filename)?;
+
+        let mut mapping = Vec::new();
+
+        for record in reader.records() {
+            let record = record?;
+            let (argument, value) = match (record.get(0), record.get(1)) {
+                (Some(argument), Some(value)) => (argument, value),
+                _ => {
+                    let line = record.position().map_or(0, |position| position.line());
+                    return Err(format!("Row on line {} needs an argument and a value.", line).into());
+                }
+            };
+            mapping.push((argument.trim().parse::<A>()?, value.trim().parse::<B>()?));
+        }
+
+        Ok(mapping)
+    }
+
+    #[test]
+    fn test_function_operations() {
+        let domain = MathSet::new(vec![1, 2, 3]);
+        let codomain = MathSet::new(vec!['a', 'b', 'c']);
+
+        // Test well-definedness
+        assert!(SetFunction::new(domain.clone(), codomain.clone(), vec![(1, 'a'), (1, 'b'), (2, 'c'), (3, 'c')]).is_err());
+        assert!(SetFunction::new(domain.clone(), codomain.clone(), vec![(1, 'a'), (2, 'b')]).is_err());
+        assert!(SetFunction::new(domain.clone(), codomain.clone(), vec![(1, 'a'), (2, 'b'), (3, 'z')]).is_err());
+
+        // Test classification
+        let bijection = SetFunction::new(domain.clone(), codomain.clone(), vec![(1, 'b'), (2, 'c'), (3, 'a')]).unwrap();
+        assert!(bijection.is_bijective());
+        assert_eq!(bijection.apply(&2), Some(&'c'));
+        assert_eq!(bijection.apply(&7), None);
+
+        let collapse = SetFunction::new(domain.clone(), codomain.clone(), vec![(1, 'a'), (2, 'a'), (3, 'b')]).unwrap();
+        assert!(!collapse.is_injective());
+        assert!(!collapse.is_surjective());
+
+        let embed = SetFunction::from_mapping(vec![(1, 'a'), (2, 'b')], codomain.clone()).unwrap();
+        assert!(embed.is_injective());
+        assert!(!embed.is_surjective());
+
+        // Test image and preimage
+        assert_eq!(collapse.image(&MathSet::new(vec![1, 2])), MathSet::new(vec!['a']));
+        assert_eq!(collapse.preimage(&MathSet::new(vec!['a', 'c'])), MathSet::new(vec![1, 2]));
+        assert_eq!(collapse.range(), MathSet::new(vec!['a', 'b']));
+
+        // Test composition and inverse
+        let inverse = bijection.inverse().unwrap();
+        assert_eq!(inverse.apply(&'a'), Some(&3));
+        let identity = bijection.compose(&inverse).unwrap();
+        assert!(domain.iter().all(|x| identity.apply(x) == Some(x)));
+        assert!(collapse.inverse().is_err());
+        let partial = SetFunction::from_mapping(vec![('a', 1), ('b', 2)], domain.clone()).unwrap();
+        assert!(bijection.compose(&partial).is_err());
+
+        // Test reading a mapping rejects rows without a value
+        let path = std::env::temp_dir().join(format!("mapping_{}.csv", std::process::id()));
+        std::fs::write(&path, "argument\n1\n2\n").unwrap();
+        let error = read_mapping_from_csv::<i32, char>(path.to_str().unwrap()).unwrap_err();
+        assert_eq!(error.to_string(), "Row on line 2 needs an argument and a value.");
+        std::fs::write(&path, "argument,value\n1,a\n 2 , b\n").unwrap();
+        assert_eq!(read_mapping_from_csv::<i32, char>(path.to_str().unwrap()).unwrap(), vec![(1, 'a'), (2, 'b')]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+pub mod interval_set {
+    use std::fmt;
+    use std::ops::Bound;
+
+    use crate::math_set::MathSet;
+
+    // A set of integers stored as sorted, disjoint, non-adjacent closed ranges, so
+    // huge or unbounded sets take space proportional to their number of gaps.
+    // Unbounded ends are clamped to the i64 range.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct IntervalSet {
+        ranges: Vec<(i64, i64)>,
+    }
+
+    impl IntervalSet {
+        pub fn empty() -> IntervalSet {
+            IntervalSet { ranges: Vec::new() }
+        }
+
+        pub fn all() -> IntervalSet {
+            IntervalSet { ranges: vec![(i64::MIN, i64::MAX)] }
+        }
+
+        // General constructor; open ends are tightened to the neighbouring integer
+        pub fn from_bounds(start: Bound<i64>, end: Bound<i64>) -> IntervalSet {
+            let start = match start {
+                Bound::Included(a) => Some(a),
+                Bound::Excluded(a) => a.checked_add(1),
+                Bound::Unbounded => Some(i64::MIN),
+            };
+            let end = match end {
+                Bound::Included(b) => Some(b),
+                Bound::Excluded(b) => b.checked_sub(1),
+                Bound::Unbounded => Some(i64::MAX),
+            };
+
+            match (start, end) {
+                (Some(a), Some(b)) if a <= b => IntervalSet { ranges: vec![(a, b)] },
+                _ => IntervalSet::empty(),
+            }
+        }
+
+        // [a, b]
+        pub fn closed(a: i64, b: i64) -> IntervalSet {
+            IntervalSet::from_bounds(Bound::Included(a), Bound::Included(b))
+        }
+
+        // (a, b)
+        pub fn open(a: i64, b: i64) -> IntervalSet {
+            IntervalSet::from_bounds(Bound::Excluded(a), Bound::Excluded(b))
+        }
+
+        // [a, b)
+        pub fn closed_open(a: i64, b: i64) -> IntervalSet {
+            IntervalSet::from_bounds(Bound::Included(a), Bound::Excluded(b))
+        }
+
+        // (a, b]
+        pub fn open_closed(a: i64, b: i64) -> IntervalSet {
+            IntervalSet::from_bounds(Bound::Excluded(a), Bound::Included(b))
+        }
+
+        // [a, +∞)
+        pub fn at_least(a: i64) -> IntervalSet {
+            IntervalSet::from_bounds(Bound::Included(a), Bound::Unbounded)
+        }
+
+        // (-∞, b]
+        pub fn at_most(b: i64) -> IntervalSet {
+            IntervalSet::from_bounds(Bound::Unbounded, Bound::Included(b))
+        }
+
+        // Inclusive (start, end) ranges in ascending order
+        pub fn ranges(&self) -> &[(i64, i64)] {
+            &self.ranges
+        }
+
+        pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+            let mut ranges = self.ranges.clone();
+            ranges.extend_from_slice(&other.ranges);
+            ranges.sort_unstable();
+
+            let mut merged: Vec<(i64, i64)> = Vec::with_capacity(ranges.len());
+            for (start, end) in ranges {
+                match merged.last_mut() {
+                    // Overlapping or adjacent ranges join into one
+                    Some(last) if start as i128 <= last.1 as i128 + 1 => last.1 = last.1.max(end),
+                    _ => merged.push((start, end)),
+                }
+            }
+
+            IntervalSet { ranges: merged }
+        }
+
+        pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+            let mut ranges = Vec::new();
+            let (mut i, mut j) = (0, 0);
+
+            while i < self.ranges.len() && j < other.ranges.len() {
+                let (a_start, a_end) = self.ranges[i];
+                let (b_start, b_end) = other.ranges[j];
+                let start = a_start.max(b_start);
+                let end = a_end.min(b_end);
+                if start <= end {
+                    ranges.push((start, end));
+                }
+                if a_end < b_end {
+                    i += 1;
+                } else {
+                    j += 1;
+                }
+            }
+
+            IntervalSet { ranges }
+        }
+
+        pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+            self.intersection(&other.complement(&IntervalSet::all()))
+        }
+
+        pub fn symmetric_difference(&self, other: &IntervalSet) -> IntervalSet {
+            self.difference(other).union(&other.difference(self))
+        }
+
+        // Elements of `universe` that are not in this set
+        pub fn complement(&self, universe: &IntervalSet) -> IntervalSet {
+            let mut gaps = Vec::with_capacity(self.ranges.len() + 1);
+            let mut next = Some(i64::MIN);
+
+            for &(start, end) in &self.ranges {
+                if let Some(gap_start) = next {
+                    if gap_start < start {
+                        gaps.push((gap_start, start - 1));
+                    }
+                }
+                next = end.checked_add(1);
+            }
+            if let Some(gap_start) = next {
+                gaps.push((gap_start, i64::MAX));
+            }
+
+            universe.intersection(&IntervalSet { ranges: gaps })
+        }
+
+        pub fn contains(&self, x: i64) -> bool {
+            let index = self.ranges.partition_point(|&(_, end)| end < x);
+            self.ranges.get(index).is_some_and(|&(start, _)| start <= x)
+        }
+
+        pub fn is_subset(&self, other: &IntervalSet) -> bool {
+            self.difference(other).is_empty()
+        }
+
+        pub fn is_disjoint(&self, other: &IntervalSet) -> bool {
+            self.intersection(other).is_empty()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.ranges.is_empty()
+        }
+
+        // Number of integers in the set; every i64 fits in a u128 count
+        pub fn cardinality(&self) -> u128 {
+            self.ranges.iter().map(|&(start, end)| (end as i128 - start as i128 + 1) as u128).sum()
+        }
+
+        pub fn from_math_set<T: Copy + Into<i64>>(set: &MathSet<T>) -> IntervalSet {
+            let mut ranges: Vec<(i64, i64)> = Vec::new();
+            for &element in set.iter() {
+                let x = element.into();
+                match ranges.last_mut() {
+                    Some(last) if last.1.checked_add(1) == Some(x) => last.1 = x,
+                    _ => ranges.push((x, x)),
+                }
+            }
+
+            IntervalSet { ranges }
+        }
+
+        // The explicit set, if it has at most `limit` elements
+        pub fn to_math_set(&self, limit: usize) -> Option<MathSet<i64>> {
+            if self.cardinality() > limit as u128 {
+                return None;
+            }
+
+            Some(self.ranges.iter().flat_map(|&(start, end)| start..=end).collect())
+        }
+    }
+
+    impl fmt::Display for IntervalSet {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if self.ranges.is_empty() {
+                return write!(f, "∅");
+            }
+
+            for (index, &(start, end)) in self.ranges.iter().enumerate() {
+                if index > 0 {
+                    write!(f, " ∪ ")?;
+                }
+                match (start, end) {
+                    (i64::MIN, i64::MAX) => write!(f, "(-∞, +∞)")?,
+                    (i64::MIN, _) => write!(f, "(-∞, {}]", end)?,
+                    (_, i64::MAX) => write!(f, "[{}, +∞)", start)?,
+                    _ if start == end => write!(f, "{{{}}}", start)?,
+                    _ => write!(f, "[{}, {}]", start, end)?,
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_interval_set_operations() {
+        // Test construction and normalization
+        assert_eq!(IntervalSet::open(1, 5), IntervalSet::closed(2, 4));
+        assert_eq!(IntervalSet::closed_open(1, 5), IntervalSet::open_closed(0, 4));
+        assert!(IntervalSet::open(1, 2).is_empty());
+        assert_eq!(IntervalSet::closed(1, 3).union(&IntervalSet::closed(4, 6)).ranges(), &[(1, 6)]);
+        assert_eq!(IntervalSet::closed(1, 3).union(&IntervalSet::closed(5, 6)).ranges(), &[(1, 3), (5, 6)]);
+
+        // Test a huge domain with a hole in it
+        let huge = IntervalSet::closed(-1_000_000_000, 1_000_000_000).difference(&IntervalSet::closed(0, 0));
+        assert_eq!(huge.ranges(), &[(-1_000_000_000, -1), (1, 1_000_000_000)]);
+        assert_eq!(huge.cardinality(), 2_000_000_000);
+        assert!(huge.contains(-5));
+        assert!(!huge.contains(0));
+        assert!(!huge.contains(1_000_000_001));
+
+        // Test set operations
+        let a = IntervalSet::closed(0, 10);
+        let b = IntervalSet::closed(5, 15);
+        assert_eq!(a.intersection(&b), IntervalSet::closed(5, 10));
+        assert_eq!(a.difference(&b), IntervalSet::closed(0, 4));
+        assert_eq!(a.symmetric_difference(&b).ranges(), &[(0, 4), (11, 15)]);
+        assert!(IntervalSet::closed(2, 3).is_subset(&a));
+        assert!(a.is_disjoint(&IntervalSet::at_least(11)));
+
+        // Test complement against bounded and unbounded universes
+        assert_eq!(a.complement(&IntervalSet::closed(-5, 20)).ranges(), &[(-5, -1), (11, 20)]);
+        let outside = a.complement(&IntervalSet::all());
+        assert_eq!(outside.ranges(), &[(i64::MIN, -1), (11, i64::MAX)]);
+        assert_eq!(outside.to_string(), "(-∞, -1] ∪ [11, +∞)");
+        assert_eq!(IntervalSet::all().cardinality(), 1u128 << 64);
+        assert!(IntervalSet::all().complement(&IntervalSet::all()).is_empty());
+        assert_eq!(IntervalSet::at_most(3).complement(&IntervalSet::all()), IntervalSet::at_least(4));
+
+        // Test conversion to and from MathSet
+        let set = MathSet::new(vec![-1, 0, 1, 5, 7, 8]);
+        let intervals = IntervalSet::from_math_set(&set);
+        assert_eq!(intervals.ranges(), &[(-1, 1), (5, 5), (7, 8)]);
+        assert_eq!(intervals.to_string(), "[-1, 1] ∪ {5} ∪ [7, 8]");
+        assert_eq!(intervals.to_math_set(10), Some(MathSet::new(vec![-1, 0, 1, 5, 7, 8])));
+        assert_eq!(huge.to_math_set(1_000), None);
+    }
+}
+
+pub mod multiset {
+    use std::io::BufRead;
+    use std::error::Error;
+    use std::fs::File;
+    use std::path::Path;
+    use std::io::BufReader;
+    use std::str::FromStr;
+    use std::cmp::Ordering;
+
+    use crate::math_set::{compare, MathSet};
+
+    // A bag: each distinct element is stored once, sorted, with its multiplicity.
+    #[derive(Debug, Clone, PartialEq, PartialOrd)]
+    pub struct MultiSet<T> {
+        counts: Vec<(T, usize)>,
+    }
+
+    impl<T: PartialOrd + Clone> MultiSet<T> {
+        pub fn new(elements: Vec<T>) -> MultiSet<T> {
+            MultiSet::from_counts(elements.into_iter().map(|element| (element, 1)).collect())
+        }
+
+        // Repeated elements have their counts added; zero counts are dropped
+        pub fn from_counts(mut counts: Vec<(T, usize)>) -> MultiSet<T> {
+            counts.retain(|(element, count)| *count > 0 && element.partial_cmp(element).is_some());
+            counts.sort_by(|a, b| compare(&a.0, &b.0));
+
+            let mut merged: Vec<(T, usize)> = Vec::with_capacity(counts.len());
+            for (element, count) in counts {
+                match merged.last_mut() {
+                    Some(last) if compare(&last.0, &element) == Ordering::Equal => last.1 += count,
+                    _ => merged.push((element, count)),
+                }
+            }
+
+            MultiSet { counts: merged }
+        }
+
+        pub fn from_math_set(set: &MathSet<T>) -> MultiSet<T> {
+            MultiSet { counts: set.iter().map(|element| (element.clone(), 1)).collect() }
+        }
+
+        // The distinct elements, forgetting multiplicities
+        pub fn to_math_set(&self) -> MathSet<T> {
+            self.counts.iter().map(|(element, _)| element.clone()).collect()
+        }
+
+        // Distinct elements with their multiplicities, in ascending order
+        pub fn counts(&self) -> &[(T, usize)] {
+            &self.counts
+        }
+
+        pub fn multiplicity(&self, element: &T) -> usize {
+            match self.position(element) {
+                Ok(index) => self.counts[index].1,
+                Err(_) => 0,
+            }
+        }
+
+        pub fn contains(&self, element: &T) -> bool {
+            self.position(element).is_ok()
+        }
+
+        // Total number of elements, counting repeats
+        pub fn cardinality(&self) -> usize {
+            self.counts.iter().map(|(_, count)| count).sum()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.counts.is_empty()
+        }
+
+        pub fn insert(&mut self, element: T) {
+            if element.partial_cmp(&element).is_none() {
+                return;
+            }
+
+            match self.position(&element) {
+                Ok(index) => self.counts[index].1 += 1,
+                Err(index) => self.counts.insert(index, (element, 1)),
+            }
+        }
+
+        // Removes one copy of `element`, returning false if there was none
+        pub fn remove(&mut self, element: &T) -> bool {
+            match self.position(element) {
+                Ok(index) => {
+                    self.counts[index].1 -= 1;
+                    if self.counts[index].1 == 0 {
+                        self.counts.remove(index);
+                    }
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+
+        // Multiplicity is the maximum of both
+        pub fn union(&self, other: &MultiSet<T>) -> MultiSet<T> {
+            self.merge(other, |a, b| a.max(b))
+        }
+
+        // Multiplicity is the sum of both
+        pub fn sum(&self, other: &MultiSet<T>) -> MultiSet<T> {
+            self.merge(other, |a, b| a + b)
+        }
+
+        // Multiplicity is the minimum of both
+        pub fn intersection(&self, other: &MultiSet<T>) -> MultiSet<T> {
+            self.merge(other, |a, b| a.min(b))
+        }
+
+        // Multiplicity is reduced by the other's, stopping at zero
+        pub fn difference(&self, other: &MultiSet<T>) -> MultiSet<T> {
+            self.merge(other, |a, b| a.saturating_sub(b))
+        }
+
+        pub fn is_subset(&self, other: &MultiSet<T>) -> bool {
+            self.counts.iter().all(|(element, count)| *count <= other.multiplicity(element))
+        }
+
+        // Walks both sorted count lists, combining the multiplicities of each element
+        fn merge(&self, other: &MultiSet<T>, combine: impl Fn(usize, usize) -> usize) -> MultiSet<T> {
+            let mut counts = Vec::new();
+            let (mut i, mut j) = (0, 0);
+
+            while i < self.counts.len() || j < other.counts.len() {
+                let order = match (self.counts.get(i), other.counts.get(j)) {
+                    (Some(a), Some(b)) => compare(&a.0, &b.0),
+                    (Some(_), None) => Ordering::Less,
+                    _ => Ordering::Greater,
+                };
+                let (element, count) = match order {
+                    Ordering::Less => {
+                        i += 1;
+                        (&self.counts[i - 1].0, combine(self.counts[i - 1].1, 0))
+                    }
+                    Ordering::Greater => {
+                        j += 1;
+                        (&other.counts[j - 1].0, combine(0, other.counts[j - 1].1))
+                    }
+                    Ordering::Equal => {
+                        i += 1;
+                        j += 1;
+                        (&self.counts[i - 1].0, combine(self.counts[i - 1].1, other.counts[j - 1].1))
+                    }
+                };
+                if count > 0 {
+                    counts.push((element.clone(), count));
+                }
+            }
+
+            MultiSet { counts }
+        }
+
+        fn position(&self, element: &T) -> Result<usize, usize> {
+            self.counts.binary_search_by(|(probe, _)| compare(probe, element))
+        }
+    }
+
+    // Same format as `read_sets_from_csv`, but repeated values are kept as multiplicities
+    pub fn read_multisets_from_csv<T>(filename: &str) -> Result<Vec<MultiSet<T>>, Box<dyn Error>>
+    where
+        T: FromStr + PartialOrd + Clone,
+        T::Err: Error + 'static,
+    {
+        let path = Path::new(filename);
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        let mut multisets = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let elements = line
+                .split(',')
+                .map(|s| s.trim().parse::<T>())
+                .collect::<Result<Vec<T>, _>>()?;
+            multisets.push(MultiSet::new(elements));
+        }
+
+        Ok(multisets)
+    }
+
+    #[test]
+    fn test_multiset_operations() {
+        let bag1 = MultiSet::new(vec![1, 1, 2, 3, 3, 3]);
+        let bag2 = MultiSet::new(vec![1, 3, 3, 4]);
+
+        // Test multiplicities
+        assert_eq!(bag1.multiplicity(&3), 3);
+        assert_eq!(bag1.multiplicity(&4), 0);
+        assert_eq!(bag1.cardinality(), 6);
+        assert_eq!(MultiSet::new(vec![2, 1, 2]), MultiSet::from_counts(vec![(1, 1), (2, 2)]));
+
+        // Test union, sum, intersection and difference
+        assert_eq!(bag1.union(&bag2).counts(), &[(1, 2), (2, 1), (3, 3), (4, 1)]);
+        assert_eq!(bag1.sum(&bag2).counts(), &[(1, 3), (2, 1), (3, 5), (4, 1)]);
+        assert_eq!(bag1.intersection(&bag2).counts(), &[(1, 1), (3, 2)]);
+        assert_eq!(bag1.difference(&bag2).counts(), &[(1, 1), (2, 1), (3, 1)]);
+        assert_eq!(bag2.difference(&bag1).counts(), &[(4, 1)]);
+        assert!(bag1.intersection(&bag2).is_subset(&bag1));
+
+        // Test insertion and removal
+        let mut bag = MultiSet::new(vec!['a']);
+        bag.insert('a');
+        bag.insert('b');
+        assert_eq!(bag.multiplicity(&'a'), 2);
+        assert!(bag.remove(&'b'));
+        assert!(!bag.contains(&'b'));
+        assert!(!bag.remove(&'b'));
+
+        // Test conversion to and from MathSet
+        assert_eq!(bag1.to_math_set(), MathSet::new(vec![1, 2, 3]));
+        let from_set = MultiSet::from_math_set(&MathSet::new(vec![5, 6]));
+        assert_eq!(from_set.counts(), &[(5, 1), (6, 1)]);
+    }
+}
+
+pub mod fuzzy_set {
+    use std::cmp::Ordering;
+
+    use crate::math_set::{compare, MathSet};
+
+    // A t-norm used for fuzzy intersection, paired with its dual t-conorm for union.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TNorm {
+        // min / max
+        Minimum,
+        // a·b / a + b − a·b
+        Product,
+        // max(0, a + b − 1) / min(1, a + b)
+        Lukasiewicz,
+    }
+
+    impl TNorm {
+        pub fn t_norm(self, a: f64, b: f64) -> f64 {
+            match self {
+                TNorm::Minimum => a.min(b),
+                TNorm::Product => a * b,
+                TNorm::Lukasiewicz => (a + b - 1.0).max(0.0),
+            }
+        }
+
+        pub fn t_conorm(self, a: f64, b: f64) -> f64 {
+            match self {
+                TNorm::Minimum => a.max(b),
+                TNorm::Product => a + b - a * b,
+                TNorm::Lukasiewicz => (a + b).min(1.0),
+            }
+        }
+    }
+
+    // A fuzzy set: elements with a membership degree in (0, 1]. Elements that are
+    // not stored have degree 0.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct FuzzySet<T> {
+        members: Vec<(T, f64)>,
+    }
+
+    impl<T: PartialOrd + Clone> FuzzySet<T> {
+        // Repeated elements keep their highest degree
+        pub fn new(members: Vec<(T, f64)>) -> Result<FuzzySet<T>, &'static str> {
+            if members.iter().any(|(_, degree)| !(0.0..=1.0).contains(degree)) {
+                return Err("Membership degrees must lie in [0, 1].");
+            }
+
+            Ok(FuzzySet::from_degrees(members))
+        }
+
+        // Crisp set with every element at degree 1
+        pub fn from_math_set(set: &MathSet<T>) -> FuzzySet<T> {
+            FuzzySet { members: set.iter().map(|element| (element.clone(), 1.0)).collect() }
+        }
+
+        // Elements with non-zero degree, in ascending order
+        pub fn members(&self) -> &[(T, f64)] {
+            &self.members
+        }
+
+        pub fn membership(&self, element: &T) -> f64 {
+            match self.members.binary_search_by(|(probe, _)| compare(probe, element)) {
+                Ok(index) => self.members[index].1,
+                Err(_) => 0.0,
+            }
+        }
+
+        // Standard (max) union
+        pub fn union(&self, other: &FuzzySet<T>) -> FuzzySet<T> {
+            self.union_with(other, TNorm::Minimum)
+        }
+
+        // Standard (min) intersection
+        pub fn intersection(&self, other: &FuzzySet<T>) -> FuzzySet<T> {
+            self.intersection_with(other, TNorm::Minimum)
+        }
+
+        pub fn union_with(&self, other: &FuzzySet<T>, norm: TNorm) -> FuzzySet<T> {
+            self.combine(other, |a, b| norm.t_conorm(a, b))
+        }
+
+        pub fn intersection_with(&self, other: &FuzzySet<T>, norm: TNorm) -> FuzzySet<T> {
+            self.combine(other, |a, b| norm.t_norm(a, b))
+        }
+
+        // Pointwise combination with any operator on [0, 1]; results are clamped
+        pub fn combine(&self, other: &FuzzySet<T>, operator: impl Fn(f64, f64) -> f64) -> FuzzySet<T> {
+            let mut members = Vec::new();
+            let (mut i, mut j) = (0, 0);
+
+            while i < self.members.len() || j < other.members.len() {
+                let order = match (self.members.get(i), other.members.get(j)) {
+                    (Some(a), Some(b)) => compare(&a.0, &b.0),
+                    (Some(_), None) => Ordering::Less,
+                    _ => Ordering::Greater,
+                };
+                let (element, a, b) = match order {
+                    Ordering::Less => {
+                        i += 1;
+                        (&self.members[i - 1].0, self.members[i - 1].1, 0.0)
+                    }
+                    Ordering::Greater => {
+                        j += 1;
+                        (&other.members[j - 1].0, 0.0, other.members[j - 1].1)
+                    }
+                    Ordering::Equal => {
+                        i += 1;
+                        j += 1;
+                        (&self.members[i - 1].0, self.members[i - 1].1, other.members[j - 1].1)
+                    }
+                };
+                members.push((element.clone(), operator(a, b).clamp(0.0, 1.0)));
+            }
+
+            FuzzySet::from_degrees(members)
+        }
+
+        // Standard complement 1 − μ(x), over the elements of `universe`
+        pub fn complement(&self, universe: &MathSet<T>) -> FuzzySet<T> {
+            let members = universe.iter().map(|x| (x.clone(), 1.0 - self.membership(x))).collect();
+            FuzzySet::from_degrees(members)
+        }
+
+        // {x : μ(x) ≥ α}
+        pub fn alpha_cut(&self, alpha: f64) -> MathSet<T> {
+            self.members.iter().filter(|(_, degree)| *degree >= alpha).map(|(x, _)| x.clone()).collect()
+        }
+
+        // {x : μ(x) > α}
+        pub fn strong_alpha_cut(&self, alpha: f64) -> MathSet<T> {
+            self.members.iter().filter(|(_, degree)| *degree > alpha).map(|(x, _)| x.clone()).collect()
+        }
+
+        pub fn support(&self) -> MathSet<T> {
+            self.strong_alpha_cut(0.0)
+        }
+
+        pub fn core(&self) -> MathSet<T> {
+            self.alpha_cut(1.0)
+        }
+
+        pub fn height(&self) -> f64 {
+            self.members.iter().map(|(_, degree)| *degree).fold(0.0, f64::max)
+        }
+
+        fn from_degrees(mut members: Vec<(T, f64)>) -> FuzzySet<T> {
+            members.retain(|(element, degree)| *degree > 0.0 && element.partial_cmp(element).is_some());
+            members.sort_by(|a, b| compare(&a.0, &b.0));
+            members.dedup_by(|next, kept| {
+                let same = compare(&kept.0, &next.0) == Ordering::Equal;
+                if same {
+                    kept.1 = kept.1.max(next.1);
+                }
+                same
+            });
+
+            FuzzySet { members }
+        }
+    }
+
+    // Defuzzification needs numeric elements
+    impl<T: PartialOrd + Clone + Into<f64>> FuzzySet<T> {
+        // Centre of gravity Σ x·μ(x) / Σ μ(x), or None for the empty set
+        pub fn centroid(&self) -> Option<f64> {
+            let weight: f64 = self.members.iter().map(|(_, degree)| degree).sum();
+            if weight == 0.0 {
+                return None;
+            }
+
+            let moment: f64 = self.members.iter().map(|(x, degree)| x.clone().into() * degree).sum();
+            Some(moment / weight)
+        }
+
+        // Average of the elements that reach the height of the set
+        pub fn mean_of_maximum(&self) -> Option<f64> {
+            let height = self.height();
+            let maxima: Vec<f64> = self
+                .members
+                .iter()
+                .filter(|(_, degree)| *degree == height)
+                .map(|(x, _)| x.clone().into())
+                .collect();
+
+            if maxima.is_empty() {
+                return None;
+            }
+            Some(maxima.iter().sum::<f64>() / maxima.len() as f64)
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_set_operations() {
+        let warm = FuzzySet::new(vec![(20, 0.2), (25, 0.8), (30, 1.0)]).unwrap();
+        let humid = FuzzySet::new(vec![(25, 0.5), (30, 0.6), (35, 0.9)]).unwrap();
+
+        // Test construction
+        assert!(FuzzySet::new(vec![(1, 1.5)]).is_err());
+        assert!(FuzzySet::new(vec![(1, f64::NAN)]).is_err());
+        assert_eq!(FuzzySet::new(vec![(1, 0.0), (2, 0.3), (2, 0.4)]).unwrap().members(), &[(2, 0.4)]);
+        assert_eq!(warm.membership(&25), 0.8);
+        assert_eq!(warm.membership(&35), 0.0);
+
+        // Test standard union and intersection
+        assert_eq!(warm.union(&humid).members(), &[(20, 0.2), (25, 0.8), (30, 1.0), (35, 0.9)]);
+        assert_eq!(warm.intersection(&humid).members(), &[(25, 0.5), (30, 0.6)]);
+
+        // Test other t-norms and t-conorms
+        let product = warm.intersection_with(&humid, TNorm::Product);
+        assert!((product.membership(&25) - 0.4).abs() < 1e-12);
+        let probabilistic = warm.union_with(&humid, TNorm::Product);
+        assert!((probabilistic.membership(&25) - 0.9).abs() < 1e-12);
+        let lukasiewicz = warm.intersection_with(&humid, TNorm::Lukasiewicz);
+        assert_eq!(lukasiewicz.members().len(), 2);
+        assert!((lukasiewicz.membership(&25) - 0.3).abs() < 1e-12);
+        assert_eq!(warm.union_with(&humid, TNorm::Lukasiewicz).membership(&30), 1.0);
+
+        // Test complement and cuts
+        let universe = MathSet::new(vec![15, 20, 25, 30]);
+        let cold = warm.complement(&universe);
+        assert_eq!(cold.membership(&15), 1.0);
+        assert_eq!(cold.membership(&30), 0.0);
+        assert_eq!(warm.alpha_cut(0.8), MathSet::new(vec![25, 30]));
+        assert_eq!(warm.strong_alpha_cut(0.8), MathSet::new(vec![30]));
+        assert_eq!(warm.core(), MathSet::new(vec![30]));
+        assert_eq!(humid.support(), MathSet::new(vec![25, 30, 35]));
+
+        // Test defuzzification
+        let centroid = warm.centroid().unwrap();
+        assert!((centroid - (20.0 * 0.2 + 25.0 * 0.8 + 30.0) / 2.0).abs() < 1e-12);
+        assert_eq!(humid.mean_of_maximum(), Some(35.0));
+        assert_eq!(FuzzySet::<i32>::new(vec![]).unwrap().centroid(), None);
+    }
+}
+
+pub mod partition {
+    use std::cmp::Ordering;
+
+    use crate::math_set::{compare, MathSet};
+
+    // A partition of a finite universe maintained as a disjoint-set forest, with
+    // path compression and union by rank.
+    #[derive(Debug, Clone)]
+    pub struct Partition<T> {
+        universe: MathSet<T>,
+        parent: Vec<usize>,
+        rank: Vec<u8>,
+    }
+
+    impl<T: PartialOrd + Clone> Partition<T> {
+        // Starts with every element in a block of its own
+        pub fn new(universe: MathSet<T>) -> Partition<T> {
+            let n = universe.cardinality();
+            Partition { universe, parent: (0..n).collect(), rank: vec![0; n] }
+        }
+
+        pub fn universe(&self) -> &MathSet<T> {
+            &self.universe
+        }
+
+        // Merges the blocks of `a` and `b`; returns false if they were already together
+        pub fn union(&mut self, a: &T, b: &T) -> Result<bool, &'static str> {
+            let (a, b) = match (self.universe.index_of(a), self.universe.index_of(b)) {
+                (Some(a), Some(b)) => (self.root(a), self.root(b)),
+                _ => return Err("Both elements must belong to the partitioned universe."),
+            };
+
+            if a == b {
+                return Ok(false);
+            }
+
+            match self.rank[a].cmp(&self.rank[b]) {
+                Ordering::Less => self.parent[a] = b,
+                Ordering::Greater => self.parent[b] = a,
+                Ordering::Equal => {
+                    self.parent[b] = a;
+                    self.rank[a] += 1;
+                }
+            }
+
+            Ok(true)
+        }
+
+        // The representative of the block containing `x`
+        pub fn find(&mut self, x: &T) -> Option<&T> {
+            let index = self.universe.index_of(x)?;
+            let root = self.root(index);
+            self.universe.elements().get(root)
+        }
+
+        pub fn same_block(&mut self, a: &T, b: &T) -> bool {
+            match (self.universe.index_of(a), self.universe.index_of(b)) {
+                (Some(a), Some(b)) => self.root(a) == self.root(b),
+                _ => false,
+            }
+        }
+
+        pub fn block_count(&self) -> usize {
+            (0..self.parent.len()).filter(|&i| self.parent[i] == i).count()
+        }
+
+        // Blocks ordered by their smallest element
+        pub fn blocks(&self) -> Vec<MathSet<T>> {
+            let elements = self.universe.elements();
+            let mut members: Vec<Vec<T>> = vec![Vec::new(); elements.len()];
+            for (index, element) in elements.iter().enumerate() {
+                members[self.root_of(index)].push(element.clone());
+            }
+
+            let mut blocks: Vec<MathSet<T>> = members.into_iter().filter(|block| !block.is_empty()).map(MathSet::new).collect();
+            blocks.sort_by(|a, b| compare(&a.elements()[0], &b.elements()[0]));
+            blocks
+        }
+
+        fn root(&mut self, index: usize) -> usize {
+            let root = self.root_of(index);
+
+            // Point everything on the path straight at the root
+            let mut current = index;
+            while self.parent[current] != root {
+                let next = self.parent[current];
+                self.parent[current] = root;
+                current = next;
+            }
+
+            root
+        }
+
+        fn root_of(&self, mut index: usize) -> usize {
+            while self.parent[index] != index {
+                index = self.parent[index];
+            }
+            index
+        }
+    }
+
+    // True if the sets are non-empty and pairwise disjoint, i.e. they partition their union
+    pub fn is_partition<T: PartialOrd + Clone>(sets: &[MathSet<T>]) -> bool {
+        if sets.iter().any(|set| set.is_empty()) {
+            return false;
+        }
+
+        let union = sets.iter().fold(MathSet::new(Vec::new()), |acc, set| acc.union(set));
+        let total: usize = sets.iter().map(|set| set.cardinality()).sum();
+        total == union.cardinality()
+    }
+
+    #[test]
+    fn test_partition_operations() {
+        let mut partition = Partition::new(MathSet::new((1..=6).collect()));
+        assert_eq!(partition.block_count(), 6);
+
+        // Test union and find
+        assert_eq!(partition.union(&1, &2), Ok(true));
+        assert_eq!(partition.union(&3, &4), Ok(true));
+        assert_eq!(partition.union(&2, &4), Ok(true));
+        assert_eq!(partition.union(&1, &3), Ok(false));
+        assert!(partition.union(&1, &9).is_err());
+        let representative = partition.find(&1).cloned();
+        assert_eq!(partition.find(&4).cloned(), representative);
+        assert_eq!(partition.find(&9), None);
+        assert!(partition.same_block(&1, &4));
+        assert!(!partition.same_block(&1, &5));
+
+        // Test blocks
+        assert_eq!(partition.block_count(), 3);
+        assert_eq!(
+            partition.blocks(),
+            vec![MathSet::new(vec![1, 2, 3, 4]), MathSet::new(vec![5]), MathSet::new(vec![6])]
+        );
+        assert!(is_partition(&partition.blocks()));
+
+        // Test partition checks
+        assert!(is_partition(&[MathSet::new(vec![1, 2]), MathSet::new(vec![3])]));
+        assert!(!is_partition(&[MathSet::new(vec![1, 2]), MathSet::new(vec![2, 3])]));
+        assert!(!is_partition(&[MathSet::new(vec![1]), MathSet::new(vec![])]));
+    }
+}
+
+pub mod bitset {
+    use crate::math_set::MathSet;
+
+    const WORD_BITS: i64 = 64;
+
+    // A set of i32 values stored as a bitmap, so the set operations work on 64
+    // elements at a time. Bit `b` of word `w` stands for `offset + 64·w + b`; the
+    // offset is a multiple of 64 and the words are trimmed, which keeps the layout
+    // unique per set and allows derived equality.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct BitSet {
+        offset: i64,
+        words: Vec<u64>,
+    }
+
+    impl BitSet {
+        pub fn new(elements: Vec<i32>) -> BitSet {
+            let (min, max) = match (elements.iter().min(), elements.iter().max()) {
+                (Some(&min), Some(&max)) => (min as i64, max as i64),
+                _ => return BitSet::default(),
+            };
+
+            let offset = min.div_euclid(WORD_BITS) * WORD_BITS;
+            let mut words = vec![0; ((max - offset) / WORD_BITS + 1) as usize];
+            for element in elements {
+                let bit = element as i64 - offset;
+                words[(bit / WORD_BITS) as usize] |= 1 << (bit % WORD_BITS);
+            }
+
+            BitSet { offset, words }
+        }
+
+        pub fn from_math_set(set: &MathSet<i32>) -> BitSet {
+            BitSet::new(set.elements().to_vec())
+        }
+
+        pub fn to_math_set(&self) -> MathSet<i32> {
+            self.iter().collect()
+        }
+
+        // Elements in ascending order
+        pub fn iter(&self) -> impl Iterator<Item = i32> + '_ {
+            self.words.iter().enumerate().flat_map(move |(index, &word)| {
+                let base = self.offset + index as i64 * WORD_BITS;
+                (0..WORD_BITS).filter(move |bit| word & (1 << bit) != 0).map(move |bit| (base + bit) as i32)
+            })
+        }
+
+        pub fn union(&self, other: &BitSet) -> BitSet {
+            self.combine(other, |a, b| a | b)
+        }
+
+        pub fn intersection(&self, other: &BitSet) -> BitSet {
+            self.combine(other, |a, b| a & b)
+        }
+
+        pub fn difference(&self, other: &BitSet) -> BitSet {
+            self.combine(other, |a, b| a & !b)
+        }
+
+        pub fn symmetric_difference(&self, other: &BitSet) -> BitSet {
+            self.combine(other, |a, b| a ^ b)
+        }
+
+        // Elements of `universe` that are not in this set
+        pub fn complement(&self, universe: &BitSet) -> BitSet {
+            universe.difference(self)
+        }
+
+        pub fn is_subset(&self, other: &BitSet) -> bool {
+            self.difference(other).is_empty()
+        }
+
+        pub fn is_proper_subset(&self, other: &BitSet) -> bool {
+            self.is_subset(other) && self != other
+        }
+
+        pub fn is_superset(&self, other: &BitSet) -> bool {
+            other.is_subset(self)
+        }
+
+        pub fn is_disjoint(&self, other: &BitSet) -> bool {
+            self.intersection(other).is_empty()
+        }
+
+        pub fn contains(&self, element: &i32) -> bool {
+            match self.locate(*element) {
+                Some((word, bit)) => self.words[word] & bit != 0,
+                None => false,
+            }
+        }
+
+        // Adds `element`, returning false if it was already present. Inside the
+        // current span only one bit changes; outside it, the bitmap grows by
+        // the words needed to reach `element`.
+        pub fn insert(&mut self, element: i32) -> bool {
+            if self.locate(element).is_none() {
+                let offset = (element as i64).div_euclid(WORD_BITS) * WORD_BITS;
+                if self.is_empty() {
+                    self.offset = offset;
+                    self.words.push(0);
+                } else if offset < self.offset {
+                    let missing = ((self.offset - offset) / WORD_BITS) as usize;
+                    self.words.splice(0..0, std::iter::repeat_n(0, missing));
+                    self.offset = offset;
+                } else {
+                    self.words.resize(((offset - self.offset) / WORD_BITS + 1) as usize, 0);
+                }
+            }
+
+            match self.locate(element) {
+                Some((word, bit)) if self.words[word] & bit == 0 => {
+                    self.words[word] |= bit;
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        // Removes `element`, returning false if it was not present
+        pub fn remove(&mut self, element: &i32) -> bool {
+            match self.locate(*element) {
+                Some((word, bit)) if self.words[word] & bit != 0 => {
+                    self.words[word] &= !bit;
+                    self.trim();
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        pub fn cardinality(&self) -> usize {
+            self.words.iter().map(|word| word.count_ones() as usize).sum()
+        }
+
+        pub fn min(&self) -> Option<i32> {
+            let first = self.words.first()?;
+            Some((self.offset + first.trailing_zeros() as i64) as i32)
+        }
+
+        pub fn max(&self) -> Option<i32> {
+            let last = self.words.last()?;
+            let base = self.offset + (self.words.len() as i64 - 1) * WORD_BITS;
+            Some((base + WORD_BITS - 1 - last.leading_zeros() as i64) as i32)
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.words.is_empty()
+        }
+
+        fn locate(&self, element: i32) -> Option<(usize, u64)> {
+            let bit = element as i64 - self.offset;
+            if bit < 0 || bit >= self.words.len() as i64 * WORD_BITS {
+                return None;
+            }
+            Some(((bit / WORD_BITS) as usize, 1 << (bit % WORD_BITS)))
+        }
+
+        // Aligns both bitmaps on a common offset and applies `operator` word by word
+        fn combine(&self, other: &BitSet, operator: impl Fn(u64, u64) -> u64) -> BitSet {
+            if self.is_empty() && other.is_empty() {
+                return BitSet::default();
+            }
+
+            let end = |set: &BitSet| set.offset + set.words.len() as i64 * WORD_BITS;
+            let (offset, limit) = match (self.is_empty(), other.is_empty()) {
+                (true, _) => (other.offset, end(other)),
+                (_, true) => (self.offset, end(self)),
+                _ => (self.offset.min(other.offset), end(self).max(end(other))),
+            };
+            let word_at = |set: &BitSet, index: i64| {
+                let local = index - (set.offset - offset) / WORD_BITS;
+                if local < 0 {
+                    0
+                } else {
+                    set.words.get(local as usize).copied().unwrap_or(0)
+                }
+            };
+
+            let words = (0..(limit - offset) / WORD_BITS)
+                .map(|index| operator(word_at(self, index), word_at(other, index)))
+                .collect();
+
+            let mut result = BitSet { offset, words };
+            result.trim();
+            result
+        }
+
+        // Drops empty words at both ends to restore the unique layout
+        fn trim(&mut self) {
+            while self.words.last() == Some(&0) {
+                self.words.pop();
+            }
+            let leading = self.words.iter().take_while(|&&word| word == 0).count();
+            self.words.drain(..leading);
+            self.offset = if self.words.is_empty() { 0 } else { self.offset + leading as i64 * WORD_BITS };
+        }
+    }
+
+    // A bitmap spends one bit per value in the range, a sorted list 32 per element
+    fn is_dense(min: i32, max: i32, len: usize) -> bool {
+        (max as i64 - min as i64) < 32 * len as i64
+    }
+
+    // An i32 set that uses a `BitSet` when the elements are dense enough for a
+    // bitmap to be smaller than the sorted list, and a `MathSet` otherwise.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum IntSet {
+        Sparse(MathSet<i32>),
+        Dense(BitSet),
+    }
+
+    impl IntSet {
+        pub fn new(elements: Vec<i32>) -> IntSet {
+            IntSet::from_math_set(MathSet::new(elements))
+        }
+
+        pub fn from_math_set(set: MathSet<i32>) -> IntSet {
+            match (set.elements().first(), set.elements().last()) {
+                (Some(&min), Some(&max)) if is_dense(min, max, set.cardinality()) => IntSet::Dense(BitSet::from_math_set(&set)),
+                _ => IntSet::Sparse(set),
+            }
+        }
+
+        pub fn from_bitset(bits: BitSet) -> IntSet {
+            match (bits.min(), bits.max()) {
+                (Some(min), Some(max)) if is_dense(min, max, bits.cardinality()) => IntSet::Dense(bits),
+                _ => IntSet::Sparse(bits.to_math_set()),
+            }
+        }
+
+        pub fn to_math_set(&self) -> MathSet<i32> {
+            match self {
+                IntSet::Sparse(set) => set.clone(),
+                IntSet::Dense(bits) => bits.to_math_set(),
+            }
+        }
+
+        pub fn is_dense(&self) -> bool {
+            matches!(self, IntSet::Dense(_))
+        }
+
+        pub fn union(&self, other: &IntSet) -> IntSet {
+            self.combine(other, BitSet::union, MathSet::union)
+        }
+
+        pub fn intersection(&self, other: &IntSet) -> IntSet {
+            self.combine(other, BitSet::intersection, MathSet::intersection)
+        }
+
+        pub fn difference(&self, other: &IntSet) -> IntSet {
+            self.combine(other, BitSet::difference, MathSet::difference)
+        }
+
+        pub fn symmetric_difference(&self, other: &IntSet) -> IntSet {
+            self.combine(other, BitSet::symmetric_difference, MathSet::symmetric_difference)
+        }
+
+        pub fn complement(&self, universe: &IntSet) -> IntSet {
+            universe.difference(self)
+        }
+
+        pub fn is_subset(&self, other: &IntSet) -> bool {
+            match (self, other) {
+                (IntSet::Dense(a), IntSet::Dense(b)) => a.is_subset(b),
+                _ => self.to_math_set().is_subset(&other.to_math_set()),
+            }
+        }
+
+        pub fn is_proper_subset(&self, other: &IntSet) -> bool {
+            self.is_subset(other) && self.cardinality() < other.cardinality()
+        }
+
+        pub fn is_superset(&self, other: &IntSet) -> bool {
+            other.is_subset(self)
+        }
+
+        pub fn is_disjoint(&self, other: &IntSet) -> bool {
+            self.intersection(other).cardinality() == 0
+        }
+
+        pub fn contains(&self, element: &i32) -> bool {
+            match self {
+                IntSet::Sparse(set) => set.contains(element),
+                IntSet::Dense(bits) => bits.contains(element),
+            }
+        }
+
+        // Switches representation when the new element changes which one is
+        // smaller, checking before a bitmap would grow to cover a far-off element
+        pub fn insert(&mut self, element: i32) -> bool {
+            if self.contains(&element) {
+                return false;
+            }
+
+            let (min, max) = match (self.min(), self.max()) {
+                (Some(min), Some(max)) => (min.min(element), max.max(element)),
+                _ => (element, element),
+            };
+            let dense = is_dense(min, max, self.cardinality() + 1);
+            match self {
+                IntSet::Dense(bits) if !dense => {
+                    let mut set = bits.to_math_set();
+                    set.insert(element);
+                    *self = IntSet::Sparse(set);
+                }
+                IntSet::Sparse(set) if dense => {
+                    let mut bits = BitSet::from_math_set(set);
+                    bits.insert(element);
+                    *self = IntSet::Dense(bits);
+                }
+                IntSet::Sparse(set) => {
+                    set.insert(element);
+                }
+                IntSet::Dense(bits) => {
+                    bits.insert(element);
+                }
+            }
+            true
+        }
+
+        pub fn min(&self) -> Option<i32> {
+            match self {
+                IntSet::Sparse(set) => set.elements().first().copied(),
+                IntSet::Dense(bits) => bits.min(),
+            }
+        }
+
+        pub fn max(&self) -> Option<i32> {
+            match self {
+                IntSet::Sparse(set) => set.elements().last().copied(),
+                IntSet::Dense(bits) => bits.max(),
+            }
+        }
+
+        // Like `insert`, re-picks the representation for what is left, so equal
+        // sets always compare equal
+        pub fn remove(&mut self, element: &i32) -> bool {
+            let removed = match self {
+                IntSet::Sparse(set) => set.remove(element),
+                IntSet::Dense(bits) => bits.remove(element),
+            };
+            if removed {
+                *self = match std::mem::replace(self, IntSet::Sparse(MathSet::new(vec![]))) {
+                    IntSet::Sparse(set) => IntSet::from_math_set(set),
+                    IntSet::Dense(bits) => IntSet::from_bitset(bits),
+                };
+            }
+            removed
+        }
+
+        pub fn cardinality(&self) -> usize {
+            match self {
+                IntSet::Sparse(set) => set.cardinality(),
+                IntSet::Dense(bits) => bits.cardinality(),
+            }
+        }
+
+        // Word-parallel when both sides are bitmaps; the result picks its own representation
+        fn combine(
+            &self,
+            other: &IntSet,
+            dense: fn(&BitSet, &BitSet) -> BitSet,
+            sparse: fn(&MathSet<i32>, &MathSet<i32>) -> MathSet<i32>,
+        ) -> IntSet {
+            match (self, other) {
+                (IntSet::Dense(a), IntSet::Dense(b)) => IntSet::from_bitset(dense(a, b)),
+                _ => IntSet::from_math_set(sparse(&self.to_math_set(), &other.to_math_set())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitset_operations() {
+        // Test negative values and word boundaries
+        let set1 = BitSet::new(vec![-65, -1, 0, 1, 63, 64]);
+        let set2 = BitSet::new(vec![-1, 1, 2, 200]);
+        assert_eq!(set1.to_math_set(), MathSet::new(vec![-65, -1, 0, 1, 63, 64]));
+        assert!(set1.contains(&-65));
+        assert!(!set1.contains(&-64));
+        assert!(!set1.contains(&1000));
+
+        // Test set operations
+        assert_eq!(set1.union(&set2), BitSet::new(vec![-65, -1, 0, 1, 2, 63, 64, 200]));
+        assert_eq!(set1.intersection(&set2), BitSet::new(vec![-1, 1]));
+        assert_eq!(set1.difference(&set2), BitSet::new(vec![-65, 0, 63, 64]));
+        assert_eq!(set1.symmetric_difference(&set2), BitSet::new(vec![-65, 0, 2, 63, 64, 200]));
+        assert_eq!(set2.complement(&BitSet::new((0..4).collect())), BitSet::new(vec![0, 3]));
+        assert!(BitSet::new(vec![-1, 64]).is_proper_subset(&set1));
+        assert!(set1.is_superset(&BitSet::new(vec![])));
+        assert!(set1.is_disjoint(&BitSet::new(vec![500])));
+
+        // Test layout stays unique after operations
+        assert_eq!(set1.difference(&set1), BitSet::default());
+        assert_eq!(set1.difference(&BitSet::new(vec![-65, -1, 0, 1])), BitSet::new(vec![63, 64]));
+
+        // Test membership and mutation
+        let mut bits = BitSet::new(vec![]);
+        assert!(bits.insert(-300));
+        assert!(!bits.insert(-300));
+        assert!(bits.insert(5));
+        assert_eq!(bits.cardinality(), 2);
+        assert!(bits.remove(&-300));
+        assert!(!bits.remove(&-300));
+        assert_eq!(bits, BitSet::new(vec![5]));
+        assert_eq!((set1.min(), set1.max()), (Some(-65), Some(64)));
+        assert_eq!(BitSet::default().min(), None);
+
+        // Test automatic representation choice
+        let dense = IntSet::new((-20..20).collect());
+        let sparse = IntSet::new(vec![-1_000_000, 0, 1_000_000]);
+        assert!(dense.is_dense());
+        assert!(!sparse.is_dense());
+        assert_eq!(dense.intersection(&sparse).to_math_set(), MathSet::new(vec![0]));
+        assert_eq!(dense.union(&sparse).cardinality(), 42);
+        assert!(IntSet::new(vec![-5, 5]).is_subset(&dense));
+        assert!(IntSet::new(vec![0]).is_proper_subset(&sparse));
+        assert!(dense.difference(&dense).to_math_set().is_empty());
+
+        // Test inserts grow the bitmap in place and keep the layout unique
+        let mut grown = BitSet::default();
+        for element in [70, 5, -130, 64, 5] {
+            grown.insert(element);
+        }
+        assert_eq!(grown, BitSet::new(vec![-130, 5, 64, 70]));
+        let mut counted = BitSet::default();
+        assert!((0..10_000).all(|element| counted.insert(element)));
+        assert_eq!(counted, BitSet::new((0..10_000).collect()));
+
+        // Test inserts switch representation as density changes
+        let mut small = IntSet::new((0..10).collect());
+        assert!(small.is_dense());
+        assert!(small.insert(i32::MAX));
+        assert!(!small.is_dense());
+        assert!(!small.insert(i32::MAX));
+        assert_eq!(small.max(), Some(i32::MAX));
+        assert_eq!(small.cardinality(), 11);
+        let mut built = IntSet::new(vec![]);
+        for element in (0..100).rev() {
+            built.insert(element);
+        }
+        assert!(built.is_dense());
+        assert_eq!(built.to_math_set(), MathSet::new((0..100).collect()));
+
+        // Test removals switch representation too, so equal sets compare equal
+        let mut hollowed = IntSet::new((0..100).collect());
+        assert!((1..99).all(|element| hollowed.remove(&element)));
+        assert!(!hollowed.remove(&50));
+        assert!(!hollowed.is_dense());
+        assert_eq!(hollowed, IntSet::new(vec![0, 99]));
+        let mut outlier = IntSet::new((0..10).chain([1_000_000]).collect());
+        assert!(!outlier.is_dense());
+        assert!(outlier.remove(&1_000_000));
+        assert_eq!(outlier, IntSet::new((0..10).collect()));
+        assert!(outlier.is_dense());
+        assert!((0..10).all(|element| outlier.remove(&element)));
+        assert_eq!(outlier, IntSet::new(vec![]));
+        assert_eq!(dense.difference(&dense), IntSet::new(vec![]));
+    }
+}
+
+pub mod venn {
+    use std::error::Error;
+    use std::fmt::Display;
+    use std::fs;
+
+    use crate::math_set::MathSet;
+
+    const NAMES: [char; 4] = ['A', 'B', 'C', 'D'];
+    const COLORS: [&str; 4] = ["#e41a1c", "#377eb8", "#4daf4a", "#984ea3"];
+
+    // One region of a Venn diagram: the elements that are in exactly the sets
+    // flagged in `membership`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct VennRegion<T> {
+        pub membership: Vec<bool>,
+        pub elements: MathSet<T>,
+    }
+
+    impl<T> VennRegion<T> {
+        // ASCII label such as `A&~B&C`
+        pub fn label(&self) -> String {
+            self.membership
+                .iter()
+                .zip(NAMES)
+                .map(|(&inside, name)| if inside { name.to_string() } else { format!("~{}", name) })
+                .collect::<Vec<_>>()
+                .join("&")
+        }
+    }
+
+    // The 2^n − 1 regions of 2 to 4 sets, from "only A" upwards
+    pub fn regions<T: PartialOrd + Clone>(sets: &[MathSet<T>]) -> Result<Vec<VennRegion<T>>, &'static str> {
+        if !(2..=4).contains(&sets.len()) {
+            return Err("Venn diagrams need between 2 and 4 sets.");
+        }
+
+        let union = sets.iter().fold(MathSet::new(Vec::new()), |acc, set| acc.union(set));
+        let mut regions: Vec<VennRegion<T>> = (1..1usize << sets.len())
+            .map(|mask| VennRegion {
+                membership: (0..sets.len()).map(|i| mask & (1 << i) != 0).collect(),
+                elements: MathSet::new(Vec::new()),
+            })
+            .collect();
+
+        for element in union.iter() {
+            let mask: usize = sets
+                .iter()
+                .enumerate()
+                .filter(|(_, set)| set.contains(element))
+                .map(|(i, _)| 1 << i)
+                .sum();
+            regions[mask - 1].elements.insert(element.clone());
+        }
+
+        Ok(regions)
+    }
+
+    // Region-by-region listing as an ASCII table
+    pub fn region_table<T: PartialOrd + Clone + Display>(sets: &[MathSet<T>]) -> Result<String, &'static str> {
+        let rows: Vec<(String, String)> = regions(sets)?
+            .iter()
+            .map(|region| (region.label(), join(&region.elements, ", ")))
+            .collect();
+
+        let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0).max("Region".len());
+        let elements_width = rows.iter().map(|(_, elements)| elements.chars().count()).max().unwrap_or(0).max("Elements".len());
+        let border = format!("+-{}-+-{}-+\n", "-".repeat(label_width), "-".repeat(elements_width));
+
+        let mut table = border.clone();
+        table += &format!("| {:<lw$} | {:<ew$} |\n", "Region", "Elements", lw = label_width, ew = elements_width);
+        table += &border;
+        for (label, elements) in rows {
+            table += &format!("| {:<lw$} | {:<ew$} |\n", label, elements, lw = label_width, ew = elements_width);
+        }
+        table += &border;
+
+        Ok(table)
+    }
+
+    // Element × set table with ∈/∉ cells, in Markdown
+    pub fn membership_table_markdown<T: PartialOrd + Clone + Display>(sets: &[MathSet<T>]) -> String {
+        let names = set_names(sets.len());
+        let mut table = format!("| Element | {} |\n", names.join(" | "));
+        table += &format!("|---|{}\n", "---|".repeat(sets.len()));
+        for (element, row) in membership_rows(sets) {
+            table += &format!("| {} | {} |\n", element, row.join(" | "));
+        }
+        table
+    }
+
+    // Element × set table with ∈/∉ cells, as CSV with a header row
+    pub fn membership_table_csv<T: PartialOrd + Clone + Display>(sets: &[MathSet<T>]) -> String {
+        let names = set_names(sets.len());
+        let mut table = format!("element,{}\n", names.join(","));
+        for (element, row) in membership_rows(sets) {
+            table += &format!("{},{}\n", element, row.join(","));
+        }
+        table
+    }
+
+    // SVG drawing with each region's elements written inside it
+    pub fn venn_svg<T: PartialOrd + Clone + Display>(sets: &[MathSet<T>]) -> Result<String, &'static str> {
+        let regions = regions(sets)?;
+        let shapes = layout(sets.len());
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\" font-family=\"sans-serif\">\n",
+            SIZE
+        );
+        svg += "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n";
+
+        for (index, shape) in shapes.iter().enumerate() {
+            svg += &format!(
+                "<ellipse cx=\"{:.1}\" cy=\"{:.1}\" rx=\"{:.1}\" ry=\"{:.1}\" transform=\"rotate({:.1} {:.1} {:.1})\" fill=\"{}\" fill-opacity=\"0.15\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+                shape.cx, shape.cy, shape.rx, shape.ry, shape.angle, shape.cx, shape.cy, COLORS[index], COLORS[index]
+            );
+            svg += &format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"20\" font-weight=\"bold\" fill=\"{}\">{}</text>\n",
+                shape.label_x, shape.label_y, COLORS[index], NAMES[index]
+            );
+        }
+
+        for region in &regions {
+            if region.elements.is_empty() {
+                continue;
+            }
+            if let Some((x, y)) = region_anchor(&shapes, &region.membership) {
+                let elements: Vec<String> = region.elements.iter().map(|element| escape(&element.to_string())).collect();
+                let lines: Vec<String> = elements.chunks(3).map(|chunk| chunk.join(", ")).collect();
+                let top = y - 7.0 * (lines.len() as f64 - 1.0);
+                svg += &format!("<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\" text-anchor=\"middle\">", x, top);
+                for (index, line) in lines.iter().enumerate() {
+                    let dy = if index == 0 { 0 } else { 14 };
+                    svg += &format!("<tspan x=\"{:.1}\" dy=\"{}\">{}</tspan>", x, dy, line);
+                }
+                svg += "</text>\n";
+            }
+        }
+
+        svg += "</svg>\n";
+        Ok(svg)
+    }
+
+    pub fn write_venn_svg<T: PartialOrd + Clone + Display>(sets: &[MathSet<T>], filename: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(filename, venn_svg(sets)?)?;
+        Ok(())
+    }
+
+    const SIZE: f64 = 500.0;
+
+    struct Shape {
+        cx: f64,
+        cy: f64,
+        rx: f64,
+        ry: f64,
+        angle: f64,
+        label_x: f64,
+        label_y: f64,
+    }
+
+    impl Shape {
+        fn contains(&self, x: f64, y: f64) -> bool {
+            let (sin, cos) = (-self.angle.to_radians()).sin_cos();
+            let (dx, dy) = (x - self.cx, y - self.cy);
+            let (u, v) = (dx * cos - dy * sin, dx * sin + dy * cos);
+            (u / self.rx).powi(2) + (v / self.ry).powi(2) <= 1.0
+        }
+    }
+
+    // Circles for two and three sets; four sets need ellipses to show all 15 regions
+    fn layout(count: usize) -> Vec<Shape> {
+        let circle = |cx: f64, cy: f64, label_x: f64, label_y: f64| Shape { cx, cy, rx: 140.0, ry: 140.0, angle: 0.0, label_x, label_y };
+        let ellipse = |cx: f64, cy: f64, angle: f64, label_x: f64, label_y: f64| Shape { cx, cy, rx: 185.0, ry: 100.0, angle, label_x, label_y };
+
+        match count {
+            2 => vec![circle(175.0, 250.0, 40.0, 120.0), circle(325.0, 250.0, 445.0, 120.0)],
+            3 => vec![
+                circle(185.0, 190.0, 30.0, 60.0),
+                circle(315.0, 190.0, 455.0, 60.0),
+                circle(250.0, 305.0, 240.0, 480.0),
+            ],
+            _ => vec![
+                ellipse(185.0, 265.0, 45.0, 20.0, 110.0),
+                ellipse(250.0, 215.0, 45.0, 110.0, 30.0),
+                ellipse(250.0, 215.0, -45.0, 375.0, 30.0),
+                ellipse(315.0, 265.0, -45.0, 465.0, 110.0),
+            ],
+        }
+    }
+
+    // A point inside the region: the sampled point nearest the region's centroid
+    fn region_anchor(shapes: &[Shape], membership: &[bool]) -> Option<(f64, f64)> {
+        let step = 5.0;
+        let points: Vec<(f64, f64)> = (0..(SIZE / step) as usize)
+            .flat_map(|i| (0..(SIZE / step) as usize).map(move |j| (i as f64 * step, j as f64 * step)))
+            .filter(|&(x, y)| shapes.iter().zip(membership).all(|(shape, &inside)| shape.contains(x, y) == inside))
+            .collect();
+
+        if points.is_empty() {
+            return None;
+        }
+
+        let count = points.len() as f64;
+        let (mean_x, mean_y) = points.iter().fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x / count, sy + y / count));
+        points.into_iter().min_by(|a, b| {
+            let distance = |p: &(f64, f64)| (p.0 - mean_x).powi(2) + (p.1 - mean_y).powi(2);
+            distance(a).total_cmp(&distance(b))
+        })
+    }
+
+    fn membership_rows<T: PartialOrd + Clone + Display>(sets: &[MathSet<T>]) -> Vec<(String, Vec<&'static str>)> {
+        let union = sets.iter().fold(MathSet::new(Vec::new()), |acc, set| acc.union(set));
+        union
+            .iter()
+            .map(|element| {
+                let row = sets.iter().map(|set| if set.contains(element) { "∈" } else { "∉" }).collect();
+                (element.to_string(), row)
+            })
+            .collect()
+    }
+
+    // A, B, C... as in the set-expression language, or S1, S2, ... when there
+    // are more sets than letters
+    fn set_names(count: usize) -> Vec<String> {
+        if count <= 26 {
+            (0..count).map(|i| ((b'A' + i as u8) as char).to_string()).collect()
+        } else {
+            (1..=count).map(|i| format!("S{}", i)).collect()
+        }
+    }
+
+    fn join<T: Display>(set: &MathSet<T>, separator: &str) -> String {
+        set.iter().map(|element| element.to_string()).collect::<Vec<_>>().join(separator)
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+    }
+
+    #[test]
+    fn test_venn_output() {
+        let a = MathSet::new(vec![1, 2, 3, 4]);
+        let b = MathSet::new(vec![3, 4, 5]);
+        let c = MathSet::new(vec![4, 6]);
+
+        // Test region computation
+        let found = regions(&[a.clone(), b.clone(), c.clone()]).unwrap();
+        assert_eq!(found.len(), 7);
+        assert_eq!(found[0].label(), "A&~B&~C");
+        assert_eq!(found[0].elements, MathSet::new(vec![1, 2]));
+        assert_eq!(found[2].elements, MathSet::new(vec![3]));
+        assert_eq!(found[6].label(), "A&B&C");
+        assert_eq!(found[6].elements, MathSet::new(vec![4]));
+        assert!(regions(&[MathSet::new(vec![1])]).is_err());
+
+        // Test ASCII table
+        let table = region_table(&[a.clone(), b.clone()]).unwrap();
+        assert!(table.contains("| A&~B   | 1, 2     |"));
+        assert!(table.contains("| A&B    | 3, 4     |"));
+
+        // Test membership tables
+        let markdown = membership_table_markdown(&[a.clone(), b.clone()]);
+        assert!(markdown.starts_with("| Element | A | B |\n|---|---|---|\n"));
+        assert!(markdown.contains("| 5 | ∉ | ∈ |"));
+        let csv = membership_table_csv(&[a.clone(), b.clone()]);
+        assert!(csv.starts_with("element,A,B\n1,∈,∉\n"));
+        assert!(membership_table_csv(&vec![a.clone(); 26]).starts_with("element,A,B,"));
+        let many = membership_table_csv(&vec![a.clone(); 200]);
+        assert!(many.starts_with("element,S1,S2,"));
+        assert!(many.lines().next().unwrap().ends_with(",S200"));
+
+        // Test every region of every layout has room for labels
+        for count in 2..=4 {
+            let shapes = layout(count);
+            for mask in 1..1usize << count {
+                let membership: Vec<bool> = (0..count).map(|i| mask & (1 << i) != 0).collect();
+                assert!(region_anchor(&shapes, &membership).is_some(), "{} sets, region {:b}", count, mask);
+            }
+        }
+
+        // Test SVG output
+        let svg = venn_svg(&[a, b, c, MathSet::new(vec![7])]).unwrap();
+        assert_eq!(svg.matches("<ellipse").count(), 4);
+        assert!(svg.contains(">1, 2</tspan>"));
+    }
+}
+
+pub mod combinatorics {
+    use std::cmp::Ordering;
+    use std::fmt;
+
+    use crate::math_set::MathSet;
+    use crate::multiset::MultiSet;
+
+    // Ordered selections of k distinct elements, in lexicographic order
+    pub fn k_permutations<T: Clone>(set: &MathSet<T>, k: usize) -> KPermutations<'_, T> {
+        let n = set.elements().len();
+        KPermutations { elements: set.elements(), indices: (0..k).collect(), done: k > n }
+    }
+
+    // Subsets of size k, in lexicographic order
+    pub fn combinations<T: Clone>(set: &MathSet<T>, k: usize) -> Combinations<'_, T> {
+        let n = set.elements().len();
+        Combinations { elements: set.elements(), indices: (0..k).collect(), done: k > n }
+    }
+
+    // Multisets of size k drawn from the set, in lexicographic order
+    pub fn combinations_with_repetition<T: Clone>(set: &MathSet<T>, k: usize) -> CombinationsWithRepetition<'_, T> {
+        let n = set.elements().len();
+        CombinationsWithRepetition { elements: set.elements(), indices: vec![0; k], done: n == 0 && k > 0 }
+    }
+
+    // Arrangements of the whole set in which no element keeps its sorted position
+    pub fn derangements<T: Clone>(set: &MathSet<T>) -> Derangements<'_, T> {
+        let n = set.elements().len();
+        Derangements { permutations: k_permutations(set, n) }
+    }
+
+    // All ways to split the set into non-empty blocks, generated from restricted
+    // growth strings
+    pub fn set_partitions<T: PartialOrd + Clone>(set: &MathSet<T>) -> SetPartitions<'_, T> {
+        let n = set.elements().len();
+        SetPartitions { elements: set.elements(), blocks: vec![0; n], done: false }
+    }
+
+    pub struct KPermutations<'a, T> {
+        elements: &'a [T],
+        indices: Vec<usize>,
+        done: bool,
+    }
+
+    impl<T: Clone> Iterator for KPermutations<'_, T> {
+        type Item = Vec<T>;
+
+        fn next(&mut self) -> Option<Vec<T>> {
+            if self.done {
+                return None;
+            }
+
+            let item = self.indices.iter().map(|&i| self.elements[i].clone()).collect();
+
+            // Bump the rightmost position that can take a larger unused index, then
+            // refill the positions after it with the smallest unused indices
+            let n = self.elements.len();
+            self.done = true;
+            for position in (0..self.indices.len()).rev() {
+                let used = &self.indices[..position];
+                let larger = (self.indices[position] + 1..n).find(|candidate| !used.contains(candidate));
+                if let Some(value) = larger {
+                    self.indices[position] = value;
+                    for next in position + 1..self.indices.len() {
+                        let used = &self.indices[..next];
+                        self.indices[next] = (0..n).find(|candidate| !used.contains(candidate)).unwrap_or(0);
+                    }
+                    self.done = false;
+                    break;
+                }
+            }
+
+            Some(item)
+        }
+    }
+
+    pub struct Combinations<'a, T> {
+        elements: &'a [T],
+        indices: Vec<usize>,
+        done: bool,
+    }
+
+    impl<T: Clone> Iterator for Combinations<'_, T> {
+        type Item = MathSet<T>;
+
+        fn next(&mut self) -> Option<MathSet<T>> {
+            if self.done {
+                return None;
+            }
+
+            let item = MathSet::from_sorted(self.indices.iter().map(|&i| self.elements[i].clone()).collect());
+
+            let (n, k) = (self.elements.len(), self.indices.len());
+            self.done = true;
+            for position in (0..k).rev() {
+                if self.indices[position] < n - k + position {
+                    self.indices[position] += 1;
+                    for next in position + 1..k {
+                        self.indices[next] = self.indices[next - 1] + 1;
+                    }
+                    self.done = false;
+                    break;
+                }
+            }
+
+            Some(item)
+        }
+    }
+
+    pub struct CombinationsWithRepetition<'a, T> {
+        elements: &'a [T],
+        indices: Vec<usize>,
+        done: bool,
+    }
+
+    impl<T: PartialOrd + Clone> Iterator for CombinationsWithRepetition<'_, T> {
+        type Item = MultiSet<T>;
+
+        fn next(&mut self) -> Option<MultiSet<T>> {
+            if self.done {
+                return None;
+            }
+
+            let item = MultiSet::new(self.indices.iter().map(|&i| self.elements[i].clone()).collect());
+
+            // Non-decreasing index sequences
+            let n = self.elements.len();
+            self.done = true;
+            for position in (0..self.indices.len()).rev() {
+                if self.indices[position] + 1 < n {
+                    let value = self.indices[position] + 1;
+                    for index in &mut self.indices[position..] {
+                        *index = value;
+                    }
+                    self.done = false;
+                    break;
+                }
+            }
+
+            Some(item)
+        }
+    }
+
+    pub struct Derangements<'a, T> {
+        permutations: KPermutations<'a, T>,
+    }
+
+    impl<T: Clone> Iterator for Derangements<'_, T> {
+        type Item = Vec<T>;
+
+        fn next(&mut self) -> Option<Vec<T>> {
+            loop {
+                if self.permutations.done {
+                    return None;
+                }
+                let fixed_point = self.permutations.indices.iter().enumerate().any(|(position, &index)| position == index);
+                let item = self.permutations.next()?;
+                if !fixed_point {
+                    return Some(item);
+                }
+            }
+        }
+    }
+
+    pub struct SetPartitions<'a, T> {
+        elements: &'a [T],
+        blocks: Vec<usize>,
+        done: bool,
+    }
+
+    impl<T: PartialOrd + Clone> Iterator for SetPartitions<'_, T> {
+        type Item = Vec<MathSet<T>>;
+
+        fn next(&mut self) -> Option<Vec<MathSet<T>>> {
+            if self.done {
+                return None;
+            }
+
+            let count = self.blocks.iter().max().map_or(0, |&max| max + 1);
+            let mut partition = vec![Vec::new(); count];
+            for (element, &block) in self.elements.iter().zip(&self.blocks) {
+                partition[block].push(element.clone());
+            }
+            let item = partition.into_iter().map(MathSet::from_sorted).collect();
+
+            // Next restricted growth string: blocks[i] <= 1 + max(blocks[..i])
+            self.done = true;
+            for position in (1..self.blocks.len()).rev() {
+                let limit = self.blocks[..position].iter().max().map_or(0, |&max| max + 1);
+                if self.blocks[position] < limit {
+                    self.blocks[position] += 1;
+                    for block in &mut self.blocks[position + 1..] {
+                        *block = 0;
+                    }
+                    self.done = false;
+                    break;
+                }
+            }
+
+            Some(item)
+        }
+    }
+
+    // Arbitrary-precision unsigned integer (little-endian base 2^32 limbs) so the
+    // counting functions below never overflow.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BigUint {
+        limbs: Vec<u32>,
+    }
+
+    impl BigUint {
+        pub fn zero() -> BigUint {
+            BigUint { limbs: Vec::new() }
+        }
+
+        pub fn one() -> BigUint {
+            BigUint::from(1)
+        }
+
+        pub fn is_zero(&self) -> bool {
+            self.limbs.is_empty()
+        }
+
+        // The value, if it fits in a u128
+        pub fn to_u128(&self) -> Option<u128> {
+            if self.limbs.len() > 4 {
+                return None;
+            }
+            Some(self.limbs.iter().rev().fold(0, |acc, &limb| (acc << 32) | limb as u128))
+        }
+
+        pub fn add(&self, other: &BigUint) -> BigUint {
+            let mut limbs = Vec::with_capacity(self.limbs.len().max(other.limbs.len()) + 1);
+            let mut carry = 0u64;
+            for i in 0..self.limbs.len().max(other.limbs.len()) {
+                let sum = carry + *self.limbs.get(i).unwrap_or(&0) as u64 + *other.limbs.get(i).unwrap_or(&0) as u64;
+                limbs.push(sum as u32);
+                carry = sum >> 32;
+            }
+            if carry > 0 {
+                limbs.push(carry as u32);
+            }
+            BigUint { limbs }
+        }
+
+        pub fn mul_small(&self, factor: u32) -> BigUint {
+            let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+            let mut carry = 0u64;
+            for &limb in &self.limbs {
+                let product = limb as u64 * factor as u64 + carry;
+                limbs.push(product as u32);
+                carry = product >> 32;
+            }
+            if carry > 0 {
+                limbs.push(carry as u32);
+            }
+            let mut result = BigUint { limbs };
+            result.trim();
+            result
+        }
+
+        // Quotient and remainder of division by a non-zero small divisor
+        pub fn div_small(&self, divisor: u32) -> (BigUint, u32) {
+            let mut limbs = vec![0; self.limbs.len()];
+            let mut remainder = 0u64;
+            for i in (0..self.limbs.len()).rev() {
+                let current = (remainder << 32) | self.limbs[i] as u64;
+                limbs[i] = (current / divisor as u64) as u32;
+                remainder = current % divisor as u64;
+            }
+            let mut quotient = BigUint { limbs };
+            quotient.trim();
+            (quotient, remainder as u32)
+        }
+
+        // mul_small and div_small for factors beyond u32, such as 2n or n + k − 1
+        fn mul_wide(&self, factor: u64) -> BigUint {
+            let mut limbs = Vec::with_capacity(self.limbs.len() + 2);
+            let mut carry = 0u128;
+            for &limb in &self.limbs {
+                let product = limb as u128 * factor as u128 + carry;
+                limbs.push(product as u32);
+                carry = product >> 32;
+            }
+            while carry > 0 {
+                limbs.push(carry as u32);
+                carry >>= 32;
+            }
+            let mut result = BigUint { limbs };
+            result.trim();
+            result
+        }
+
+        fn div_wide(&self, divisor: u64) -> BigUint {
+            let mut limbs = vec![0; self.limbs.len()];
+            let mut remainder = 0u128;
+            for i in (0..self.limbs.len()).rev() {
+                let current = (remainder << 32) | self.limbs[i] as u128;
+                limbs[i] = (current / divisor as u128) as u32;
+                remainder = current % divisor as u128;
+            }
+            let mut quotient = BigUint { limbs };
+            quotient.trim();
+            quotient
+        }
+
+        fn trim(&mut self) {
+            while self.limbs.last() == Some(&0) {
+                self.limbs.pop();
+            }
+        }
+    }
+
+    impl From<u64> for BigUint {
+        fn from(value: u64) -> BigUint {
+            let mut result = BigUint { limbs: vec![value as u32, (value >> 32) as u32] };
+            result.trim();
+            result
+        }
+    }
+
+    impl PartialOrd for BigUint {
+        fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for BigUint {
+        fn cmp(&self, other: &BigUint) -> Ordering {
+            self.limbs.len().cmp(&other.limbs.len()).then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+        }
+    }
+
+    impl fmt::Display for BigUint {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if self.is_zero() {
+                return write!(f, "0");
+            }
+
+            // Peel off base-10^9 digits, least significant first
+            let mut chunks = Vec::new();
+            let mut value = self.clone();
+            while !value.is_zero() {
+                let (quotient, remainder) = value.div_small(1_000_000_000);
+                chunks.push(remainder);
+                value = quotient;
+            }
+
+            write!(f, "{}", chunks.last().unwrap_or(&0))?;
+            for chunk in chunks.iter().rev().skip(1) {
+                write!(f, "{:09}", chunk)?;
+            }
+            Ok(())
+        }
+    }
+
+    pub fn factorial(n: u32) -> BigUint {
+        (2..=n).fold(BigUint::one(), |acc, i| acc.mul_small(i))
+    }
+
+    // n choose k
+    pub fn binomial(n: u32, k: u32) -> BigUint {
+        binomial_wide(n as u64, k as u64)
+    }
+
+    // Takes u64 so that callers can pass 2n or n + k − 1 without overflowing u32
+    fn binomial_wide(n: u64, k: u64) -> BigUint {
+        if k > n {
+            return BigUint::zero();
+        }
+
+        // Each partial product is itself a binomial coefficient, so the division is exact
+        let k = k.min(n - k);
+        (1..=k).fold(BigUint::one(), |acc, i| acc.mul_wide(n - k + i).div_wide(i))
+    }
+
+    // n!/(n−k)!, the number of k-permutations
+    pub fn permutation_count(n: u32, k: u32) -> BigUint {
+        if k > n {
+            return BigUint::zero();
+        }
+        (n - k + 1..=n).fold(BigUint::one(), |acc, i| acc.mul_small(i))
+    }
+
+    // Multisets of size k from n kinds: C(n + k − 1, k)
+    pub fn multichoose(n: u32, k: u32) -> BigUint {
+        if n == 0 {
+            return if k == 0 { BigUint::one() } else { BigUint::zero() };
+        }
+        binomial_wide(n as u64 + k as u64 - 1, k as u64)
+    }
+
+    // !n, via !n = (n − 1)(!(n − 1) + !(n − 2))
+    pub fn derangement_count(n: u32) -> BigUint {
+        let (mut previous, mut current) = (BigUint::one(), BigUint::zero());
+        if n == 0 {
+            return previous;
+        }
+        for i in 2..=n {
+            let next = previous.add(&current).mul_small(i - 1);
+            previous = current;
+            current = next;
+        }
+        current
+    }
+
+    // Unsigned Stirling numbers of the first kind: permutations of n with k cycles
+    pub fn stirling_first(n: u32, k: u32) -> BigUint {
+        stirling_table(n, k, |i, _| i)
+    }
+
+    // Stirling numbers of the second kind: partitions of n elements into k blocks
+    pub fn stirling_second(n: u32, k: u32) -> BigUint {
+        stirling_table(n, k, |_, j| j)
+    }
+
+    // Number of partitions of an n-element set
+    pub fn bell(n: u32) -> BigUint {
+        (0..=n).fold(BigUint::zero(), |acc, k| acc.add(&stirling_second(n, k)))
+    }
+
+    // C(2n, n) / (n + 1)
+    pub fn catalan(n: u32) -> BigUint {
+        binomial_wide(2 * n as u64, n as u64).div_wide(n as u64 + 1)
+    }
+
+    // Both Stirling recurrences have the form S(i+1, j) = weight(i, j)·S(i, j) + S(i, j−1)
+    fn stirling_table(n: u32, k: u32, weight: impl Fn(u32, u32) -> u32) -> BigUint {
+        if k > n {
+            return BigUint::zero();
+        }
+
+        let mut row = vec![BigUint::one()];
+        for i in 0..n {
+            let mut next = vec![BigUint::zero(); row.len() + 1];
+            for j in 1..next.len() {
+                let kept = row.get(j).map_or(BigUint::zero(), |value| value.mul_small(weight(i, j as u32)));
+                next[j] = kept.add(&row[j - 1]);
+            }
+            row = next;
+        }
+        row[k as usize].clone()
+    }
+
+    #[test]
+    fn test_combinatorics() {
+        let set = MathSet::new(vec![1, 2, 3, 4]);
+
+        // Test enumeration
+        let permutations: Vec<Vec<i32>> = k_permutations(&set, 2).collect();
+        assert_eq!(permutations.len(), 12);
+        assert_eq!(permutations[0], vec![1, 2]);
+        assert_eq!(permutations[3], vec![2, 1]);
+        assert_eq!(k_permutations(&set, 4).count(), 24);
+        assert_eq!(k_permutations(&set, 0).collect::<Vec<_>>(), vec![Vec::<i32>::new()]);
+        assert_eq!(k_permutations(&set, 5).count(), 0);
+
+        let pairs: Vec<MathSet<i32>> = combinations(&set, 2).collect();
+        assert_eq!(pairs.len(), 6);
+        assert_eq!(pairs[0], MathSet::new(vec![1, 2]));
+        assert_eq!(pairs[5], MathSet::new(vec![3, 4]));
+
+        let bags: Vec<MultiSet<i32>> = combinations_with_repetition(&MathSet::new(vec![1, 2, 3]), 2).collect();
+        assert_eq!(bags.len(), 6);
+        assert_eq!(bags[0], MultiSet::new(vec![1, 1]));
+        assert_eq!(bags[5], MultiSet::new(vec![3, 3]));
+
+        let deranged: Vec<Vec<i32>> = derangements(&MathSet::new(vec![1, 2, 3])).collect();
+        assert_eq!(deranged, vec![vec![2, 3, 1], vec![3, 1, 2]]);
+        assert_eq!(derangements(&set).count(), 9);
+
+        let partitions: Vec<Vec<MathSet<i32>>> = set_partitions(&MathSet::new(vec![1, 2, 3])).collect();
+        assert_eq!(partitions.len(), 5);
+        assert_eq!(partitions[0], vec![MathSet::new(vec![1, 2, 3])]);
+        assert_eq!(partitions[4], vec![MathSet::new(vec![1]), MathSet::new(vec![2]), MathSet::new(vec![3])]);
+        assert_eq!(set_partitions(&set).count(), 15);
+
+        // Test exact counting
+        assert_eq!(binomial(4, 2), BigUint::from(6));
+        assert_eq!(binomial(3, 5), BigUint::zero());
+        assert_eq!(binomial(100, 50).to_string(), "100891344545564193334812497256");
+        assert_eq!(factorial(25).to_string(), "15511210043330985984000000");
+        assert_eq!(permutation_count(4, 2), BigUint::from(12));
+        assert_eq!(multichoose(3, 2), BigUint::from(6));
+        assert_eq!(derangement_count(4), BigUint::from(9));
+        assert_eq!(derangement_count(0), BigUint::one());
+        assert_eq!(stirling_first(4, 2), BigUint::from(11));
+        assert_eq!(stirling_second(4, 2), BigUint::from(7));
+        assert_eq!(bell(4), BigUint::from(15));
+        assert_eq!(catalan(5), BigUint::from(42));
+        assert_eq!(catalan(100).to_string(), "896519947090131496687170070074100632420837521538745909320");
+        // Arguments near u32::MAX, where n + k − 1 and the intermediate factors leave u32
+        assert_eq!(multichoose(u32::MAX, 2), BigUint::from((1u64 << 32) * (u32::MAX as u64) / 2));
+        assert_eq!(multichoose(u32::MAX, 1), BigUint::from(u32::MAX as u64));
+        assert_eq!(binomial(u32::MAX, 2), BigUint::from(u32::MAX as u64 * (u32::MAX as u64 - 1) / 2));
+        assert_eq!(bell(30).to_u128(), Some(846749014511809332450147));
+        assert!(factorial(40).to_u128().is_none());
+        assert!(binomial(60, 30) > binomial(59, 30));
+    }
+}
+
+pub mod inclusion_exclusion {
+    use std::fmt;
+
+    use crate::math_set::MathSet;
+
+    // One term |A_i ∩ A_j ∩ ...| of the expansion; `indices` are 0-based positions
+    // of the sets involved.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Term {
+        pub indices: Vec<usize>,
+        pub cardinality: u64,
+    }
+
+    impl Term {
+        // Added for an odd number of sets, subtracted for an even number
+        pub fn sign(&self) -> i64 {
+            if self.indices.len() % 2 == 1 { 1 } else { -1 }
+        }
+    }
+
+    // |A1 ∪ ... ∪ An| together with every intersection term used to compute it,
+    // ordered by the number of sets and then lexicographically.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct InclusionExclusion {
+        pub terms: Vec<Term>,
+        pub total: i64,
+    }
+
+    // Expands |A1 ∪ ... ∪ An| from the sets themselves
+    pub fn union_cardinality<T: PartialOrd + Clone>(sets: &[MathSet<T>]) -> Result<InclusionExclusion, &'static str> {
+        let terms = subsets(sets.len())?
+            .into_iter()
+            .map(|indices| {
+                let mut intersection = sets[indices[0]].clone();
+                for &index in &indices[1..] {
+                    intersection = intersection.intersection(&sets[index]);
+                }
+                Term { cardinality: intersection.cardinality() as u64, indices }
+            })
+            .collect();
+
+        Ok(InclusionExclusion::from_terms(terms))
+    }
+
+    // Expands |A1 ∪ ... ∪ An| from known intersection sizes, e.g. ([0, 1], 4) for
+    // |A1 ∩ A2| = 4. Every non-empty combination of the n sets must be given.
+    pub fn from_cardinalities(n: usize, counts: &[(Vec<usize>, u64)]) -> Result<InclusionExclusion, &'static str> {
+        if counts.iter().any(|(indices, _)| indices.iter().any(|&index| index >= n)) {
+            return Err("Intersection refers to a set index outside 0..n.");
+        }
+
+        let mut terms = Vec::new();
+        for indices in subsets(n)? {
+            let count = counts.iter().find(|(given, _)| {
+                let mut given = given.clone();
+                given.sort_unstable();
+                given.dedup();
+                given == indices
+            });
+            match count {
+                Some((_, cardinality)) => terms.push(Term { indices, cardinality: *cardinality }),
+                None => return Err("Missing the size of at least one intersection."),
+            }
+        }
+
+        Ok(InclusionExclusion::from_terms(terms))
+    }
+
+    impl InclusionExclusion {
+        fn from_terms(terms: Vec<Term>) -> InclusionExclusion {
+            let total = terms.iter().map(|term| term.sign() * term.cardinality as i64).sum();
+            InclusionExclusion { terms, total }
+        }
+    }
+
+    // Step-by-step form, e.g. |A1 ∪ A2| = |A1| + |A2| − |A1 ∩ A2| = 3 + 3 − 1 = 5
+    impl fmt::Display for InclusionExclusion {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let n = self.terms.iter().map(|term| term.indices.len()).max().unwrap_or(0);
+            let name = |indices: &[usize], separator: &str| {
+                indices.iter().map(|index| format!("A{}", index + 1)).collect::<Vec<_>>().join(separator)
+            };
+
+            write!(f, "|{}| =", name(&(0..n).collect::<Vec<_>>(), " ∪ "))?;
+            for (position, term) in self.terms.iter().enumerate() {
+                let operator = if term.sign() > 0 { "+" } else { "−" };
+                if position == 0 {
+                    write!(f, " |{}|", name(&term.indices, " ∩ "))?;
+                } else {
+                    write!(f, " {} |{}|", operator, name(&term.indices, " ∩ "))?;
+                }
+            }
+
+            write!(f, " =")?;
+            for (position, term) in self.terms.iter().enumerate() {
+                let operator = if term.sign() > 0 { "+" } else { "−" };
+                if position == 0 {
+                    write!(f, " {}", term.cardinality)?;
+                } else {
+                    write!(f, " {} {}", operator, term.cardinality)?;
+                }
+            }
+
+            write!(f, " = {}", self.total)
+        }
+    }
+
+    // Non-empty subsets of 0..n, by size and then lexicographically
+    fn subsets(n: usize) -> Result<Vec<Vec<usize>>, &'static str> {
+        if n == 0 {
+            return Err("Inclusion-exclusion needs at least one set.");
+        }
+        if n >= usize::BITS as usize {
+            return Err("Too many sets to enumerate every intersection.");
+        }
+
+        let mut subsets: Vec<Vec<usize>> = (1..1usize << n)
+            .map(|mask| (0..n).filter(|i| mask & (1 << i) != 0).collect())
+            .collect();
+        subsets.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        Ok(subsets)
+    }
+
+    #[test]
+    fn test_inclusion_exclusion() {
+        let sets = vec![
+            MathSet::new(vec![1, 2, 3, 4]),
+            MathSet::new(vec![3, 4, 5]),
+            MathSet::new(vec![4, 5, 6, 7]),
+        ];
+
+        // Test against the direct union
+        let result = union_cardinality(&sets).unwrap();
+        let union = sets[0].union(&sets[1]).union(&sets[2]);
+        assert_eq!(result.total, union.cardinality() as i64);
+        assert_eq!(result.terms.len(), 7);
+        assert_eq!(result.terms[3], Term { indices: vec![0, 1], cardinality: 2 });
+        assert_eq!(result.terms[6].sign(), 1);
+
+        // Test step-by-step output
+        let pair = union_cardinality(&sets[..2]).unwrap();
+        assert_eq!(pair.to_string(), "|A1 ∪ A2| = |A1| + |A2| − |A1 ∩ A2| = 4 + 3 − 2 = 5");
+
+        // Test cardinalities only: 30 study French, 25 German, 10 both
+        let textbook = from_cardinalities(2, &[(vec![0], 30), (vec![1], 25), (vec![1, 0], 10)]).unwrap();
+        assert_eq!(textbook.total, 45);
+        assert!(from_cardinalities(2, &[(vec![0], 30), (vec![1], 25)]).is_err());
+        assert!(from_cardinalities(1, &[(vec![0], 30), (vec![3], 25)]).is_err());
+
+        // Test that no sets, or more than the subset masks can hold, are rejected
+        assert!(union_cardinality::<i32>(&[]).is_err());
+        assert!(from_cardinalities(0, &[]).is_err());
+        assert!(from_cardinalities(usize::BITS as usize, &[]).is_err());
+        assert!(union_cardinality(&vec![MathSet::new(vec![1]); usize::BITS as usize]).is_err());
+    }
+}
+
+pub mod matrix  {
+    use std::io::BufRead;
+    use std::error::Error;
+    use std::fs::File;
+    use std::path::Path;
+    use std::io::BufReader;
+    use std::fmt;
+    use std::fmt::Debug;
+    use std::ops::{Index, IndexMut};
+    use std::str::FromStr;
+
+    use crate::complex::Complex;
+    use crate::rational::Rational;
+
+    // Element types a `Matrix` can hold. The arithmetic mirrors the by-reference
+    // `add`/`subtract`/`multiply` methods of `Complex`.
+    pub trait Numeric: Clone + PartialEq + Debug {
+        fn zero() -> Self;
+        fn one() -> Self;
+        fn add(&self, other: &Self) -> Self;
+        fn subtract(&self, other: &Self) -> Self;
+        fn multiply(&self, other: &Self) -> Self;
+    }
+
+    macro_rules! impl_numeric {
+        ($($t:ty),*) => {
+            $(
+                impl Numeric for $t {
+                    fn zero() -> Self {
+                        0 as $t
+                    }
+
+                    fn one() -> Self {
+                        1 as $t
+                    }
+
+                    fn add(&self, other: &Self) -> Self {
+                        self + other
+                    }
+
+                    fn subtract(&self, other: &Self) -> Self {
+                        self - other
+                    }
+
+                    fn multiply(&self, other: &Self) -> Self {
+                        self * other
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_numeric!(i8, i16, i32, i64, i128, isize, f32, f64);
+
+    impl Numeric for Complex {
+        fn zero() -> Self {
+            Complex::new(0.0, 0.0)
+        }
+
+        fn one() -> Self {
+            Complex::new(1.0, 0.0)
+        }
+
+        fn add(&self, other: &Self) -> Self {
+            Complex::add(self, other)
+        }
+
+        fn subtract(&self, other: &Self) -> Self {
+            Complex::subtract(self, other)
+        }
+
+        fn multiply(&self, other: &Self) -> Self {
+            Complex::multiply(self, other)
+        }
+    }
+
+    impl Numeric for Rational {
+        fn zero() -> Self {
+            Rational::zero()
+        }
+
+        fn one() -> Self {
+            Rational::one()
+        }
+
+        fn add(&self, other: &Self) -> Self {
+            Rational::add(self, other)
+        }
+
+        fn subtract(&self, other: &Self) -> Self {
+            Rational::subtract(self, other)
+        }
+
+        fn multiply(&self, other: &Self) -> Self {
+            Rational::multiply(self, other)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum MatrixError {
+        // Shapes (rows, cols) that `op` cannot combine
+        DimensionMismatch { op: &'static str, left: (usize, usize), right: (usize, usize) },
+        NotSquare { rows: usize, cols: usize },
+        Singular,
+        NotSymmetric,
+        NotPositiveDefinite,
+        // An iterative method hit its iteration limit
+        NoConvergence { op: &'static str },
+        // Linear system with no solution
+        Inconsistent,
+        // An operand has no rows or no columns
+        Empty,
+        Overflow { op: &'static str, row: usize, col: usize },
+        // Unparseable cell; line and column are 1-based positions in the CSV file
+        Parse { line: usize, col: usize },
+        // Row of a different length than the first row; `line` is 1-based
+        RaggedRow { line: usize, expected: usize, found: usize },
+        DataLength { expected: usize, found: usize },
+    }
+
+    impl fmt::Display for MatrixError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                MatrixError::DimensionMismatch { op, left, right } => write!(
+                    f,
+                    "cannot apply {} to a {}×{} and a {}×{} matrix",
+                    op, left.0, left.1, right.0, right.1
+                ),
+                MatrixError::NotSquare { rows, cols } => write!(f, "expected a square matrix, found {}×{}", rows, cols),
+                MatrixError::Singular => write!(f, "matrix is singular"),
+                MatrixError::NotSymmetric => write!(f, "matrix is not symmetric"),
+                MatrixError::NotPositiveDefinite => write!(f, "matrix is not positive definite"),
+                MatrixError::NoConvergence { op } => write!(f, "{} did not converge", op),
+                MatrixError::Inconsistent => write!(f, "system is inconsistent and has no solution"),
+                MatrixError::Empty => write!(f, "matrix has no elements"),
+                MatrixError::Overflow { op, row, col } => {
+                    write!(f, "{} overflowed at row {}, column {}", op, row, col)
+                }
+                MatrixError::Parse { line, col } => write!(f, "invalid number at line {}, column {}", line, col),
+                MatrixError::RaggedRow { line, expected, found } => {
+                    write!(f, "row on line {} has {} entries, expected {}", line, found, expected)
+                }
+                MatrixError::DataLength { expected, found } => {
+                    write!(f, "expected {} elements, found {}", expected, found)
+                }
+            }
+        }
+    }
+
+    impl Error for MatrixError {}
+
+    // Dense matrix stored row-major in one contiguous buffer.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Matrix<T> {
+        rows: usize,
+        cols: usize,
+        data: Vec<T>,
+    }
+
+    // Tile edge for the multiplication kernel; three 64×64 tiles of f64 fit in L2
+    const BLOCK: usize = 64;
+
+    impl<T: Numeric> Matrix<T> {
+        pub fn new(rows: usize, cols: usize) -> Matrix<T> {
+            Matrix {
+                rows,
+                cols,
+                data: vec![T::zero(); rows * cols],
+            }
+        }
+
+        pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Matrix<T>, MatrixError> {
+            let cols = rows.first().map_or(0, |row| row.len());
+            if let Some(index) = rows.iter().position(|row| row.len() != cols) {
+                return Err(MatrixError::RaggedRow { line: index + 1, expected: cols, found: rows[index].len() });
+            }
+
+            Ok(Matrix {
+                rows: rows.len(),
+                cols,
+                data: rows.into_iter().flatten().collect(),
+            })
+        }
+
+        pub fn from_vec(rows: usize, cols: usize, data: Vec<T>) -> Result<Matrix<T>, MatrixError> {
+            if data.len() != rows * cols {
+                return Err(MatrixError::DataLength { expected: rows * cols, found: data.len() });
+            }
+
+            Ok(Matrix { rows, cols, data })
+        }
+
+        pub fn rows(&self) -> usize {
+            self.rows
+        }
+
+        pub fn cols(&self) -> usize {
+            self.cols
+        }
+
+        pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+            if row < self.rows && col < self.cols {
+                self.data.get(row * self.cols + col)
+            } else {
+                None
+            }
+        }
+
+        pub fn row(&self, row: usize) -> &[T] {
+            &self.data[row * self.cols..(row + 1) * self.cols]
+        }
+
+        // Row-major view of every element
+        pub fn as_slice(&self) -> &[T] {
+            &self.data
+        }
+
+        pub fn to_rows(&self) -> Vec<Vec<T>> {
+            (0..self.rows).map(|row| self.row(row).to_vec()).collect()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.rows == 0 || self.cols == 0
+        }
+
+        pub fn add(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.check_same_shape(other, "addition")?;
+
+            let data = self.data.iter().zip(&other.data).map(|(a, b)| a.add(b)).collect();
+            Ok(Matrix { rows: self.rows, cols: self.cols, data })
+        }
+
+        pub fn subtract(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.check_same_shape(other, "subtraction")?;
+
+            let data = self.data.iter().zip(&other.data).map(|(a, b)| a.subtract(b)).collect();
+            Ok(Matrix { rows: self.rows, cols: self.cols, data })
+        }
+
+        // Tiled i-k-j product: each tile of `other` is reused while it is in cache and
+        // the innermost loop walks both `other` and the result row contiguously
+        pub fn multiply(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.check_product(other)?;
+
+            let (n, inner, m) = (self.rows, self.cols, other.cols);
+            let mut result: Matrix<T> = Matrix::new(n, m);
+
+            for row_block in (0..n).step_by(BLOCK) {
+                for inner_block in (0..inner).step_by(BLOCK) {
+                    for col_block in (0..m).step_by(BLOCK) {
+                        let col_end = (col_block + BLOCK).min(m);
+                        for i in row_block..(row_block + BLOCK).min(n) {
+                            let out = &mut result.data[i * m + col_block..i * m + col_end];
+                            for k in inner_block..(inner_block + BLOCK).min(inner) {
+                                let a = &self.data[i * inner + k];
+                                let b = &other.data[k * m + col_block..k * m + col_end];
+                                for (cell, b) in out.iter_mut().zip(b) {
+                                    *cell = cell.add(&a.multiply(b));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            Ok(result)
+        }
+
+        pub fn scalar_multiply(&self, scalar: T) -> Matrix<T> {
+            let data = self.data.iter().map(|a| a.multiply(&scalar)).collect();
+            Matrix { rows: self.rows, cols: self.cols, data }
+        }
+
+        pub fn zeros(rows: usize, cols: usize) -> Matrix<T> {
+            Matrix::new(rows, cols)
+        }
+
+        pub fn identity(size: usize) -> Matrix<T> {
+            let mut matrix = Matrix::new(size, size);
+            for i in 0..size {
+                matrix[(i, i)] = T::one();
+            }
+            matrix
+        }
+
+        pub fn map<U: Numeric>(&self, f: impl Fn(&T) -> U) -> Matrix<U> {
+            Matrix { rows: self.rows, cols: self.cols, data: self.data.iter().map(f).collect() }
+        }
+
+        pub fn column(&self, col: usize) -> Vec<T> {
+            (0..self.rows).map(|i| self.data[i * self.cols + col].clone()).collect()
+        }
+
+        // The given columns, in the given order
+        pub fn select_columns(&self, columns: &[usize]) -> Matrix<T> {
+            let mut data = Vec::with_capacity(self.rows * columns.len());
+            for i in 0..self.rows {
+                data.extend(columns.iter().map(|&j| self.data[i * self.cols + j].clone()));
+            }
+            Matrix { rows: self.rows, cols: columns.len(), data }
+        }
+
+        pub fn is_square(&self) -> bool {
+            self.rows == self.cols
+        }
+
+        pub fn transpose(&self) -> Matrix<T> {
+            let mut data = Vec::with_capacity(self.data.len());
+            for j in 0..self.cols {
+                for i in 0..self.rows {
+                    data.push(self.data[i * self.cols + j].clone());
+                }
+            }
+            Matrix { rows: self.cols, cols: self.rows, data }
+        }
+
+        pub fn trace(&self) -> Result<T, MatrixError> {
+            self.check_square()?;
+            Ok((0..self.rows).fold(T::zero(), |sum, i| sum.add(&self[(i, i)])))
+        }
+
+        // Non-empty and square, as determinants, traces and inverses require
+        fn check_square(&self) -> Result<(), MatrixError> {
+            if self.is_empty() {
+                return Err(MatrixError::Empty);
+            }
+            if !self.is_square() {
+                return Err(MatrixError::NotSquare { rows: self.rows, cols: self.cols });
+            }
+            Ok(())
+        }
+
+        fn swap_rows(&mut self, a: usize, b: usize) {
+            if a != b {
+                for j in 0..self.cols {
+                    self.data.swap(a * self.cols + j, b * self.cols + j);
+                }
+            }
+        }
+
+        // Both operands must be non-empty and of the same shape
+        fn check_same_shape(&self, other: &Matrix<T>, op: &'static str) -> Result<(), MatrixError> {
+            if self.is_empty() || other.is_empty() {
+                return Err(MatrixError::Empty);
+            }
+            if self.rows != other.rows || self.cols != other.cols {
+                return Err(MatrixError::DimensionMismatch { op, left: (self.rows, self.cols), right: (other.rows, other.cols) });
+            }
+            Ok(())
+        }
+
+        // Both operands must be non-empty with matching inner dimensions
+        fn check_product(&self, other: &Matrix<T>) -> Result<(), MatrixError> {
+            if self.is_empty() || other.is_empty() {
+                return Err(MatrixError::Empty);
+            }
+            if self.cols != other.rows {
+                return Err(MatrixError::DimensionMismatch {
+                    op: "multiplication",
+                    left: (self.rows, self.cols),
+                    right: (other.rows, other.cols),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    impl<T> Index<(usize, usize)> for Matrix<T> {
+        type Output = T;
+
+        fn index(&self, (row, col): (usize, usize)) -> &T {
+            assert!(row < self.rows && col < self.cols, "matrix index out of bounds");
+            &self.data[row * self.cols + col]
+        }
+    }
+
+    impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+        fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+            assert!(row < self.rows && col < self.cols, "matrix index out of bounds");
+            &mut self.data[row * self.cols + col]
+        }
+    }
+
+    // Integer element types, with the overflow-aware primitives of the standard library.
+    pub trait Integer: Numeric + Copy + Ord {
+        fn min_value() -> Self;
+        fn max_value() -> Self;
+        fn checked_add(self, other: Self) -> Option<Self>;
+        fn checked_sub(self, other: Self) -> Option<Self>;
+        fn checked_mul(self, other: Self) -> Option<Self>;
+        fn checked_div(self, other: Self) -> Option<Self>;
+        fn saturating_add(self, other: Self) -> Self;
+        fn saturating_sub(self, other: Self) -> Self;
+        fn saturating_mul(self, other: Self) -> Self;
+        fn wrapping_add(self, other: Self) -> Self;
+        fn wrapping_sub(self, other: Self) -> Self;
+        fn wrapping_mul(self, other: Self) -> Self;
+    }
+
+    macro_rules! impl_integer {
+        ($($t:ty),*) => {
+            $(
+                impl Integer for $t {
+                    fn min_value() -> Self { <$t>::MIN }
+                    fn max_value() -> Self { <$t>::MAX }
+                    fn checked_add(self, other: Self) -> Option<Self> { <$t>::checked_add(self, other) }
+                    fn checked_sub(self, other: Self) -> Option<Self> { <$t>::checked_sub(self, other) }
+                    fn checked_mul(self, other: Self) -> Option<Self> { <$t>::checked_mul(self, other) }
+                    fn checked_div(self, other: Self) -> Option<Self> { <$t>::checked_div(self, other) }
+                    fn saturating_add(self, other: Self) -> Self { <$t>::saturating_add(self, other) }
+                    fn saturating_sub(self, other: Self) -> Self { <$t>::saturating_sub(self, other) }
+                    fn saturating_mul(self, other: Self) -> Self { <$t>::saturating_mul(self, other) }
+                    fn wrapping_add(self, other: Self) -> Self { <$t>::wrapping_add(self, other) }
+                    fn wrapping_sub(self, other: Self) -> Self { <$t>::wrapping_sub(self, other) }
+                    fn wrapping_mul(self, other: Self) -> Self { <$t>::wrapping_mul(self, other) }
+                }
+            )*
+        };
+    }
+
+    impl_integer!(i8, i16, i32, i64, i128, isize);
+
+    // Integer types with a wider type that can hold their products exactly.
+    pub trait Widen: Integer {
+        type Wide: Integer;
+        fn widen(self) -> Self::Wide;
+        // The wide value clamped to this type's range
+        fn narrow_saturating(wide: Self::Wide) -> Self;
+    }
+
+    macro_rules! impl_widen {
+        ($($t:ty => $wide:ty),*) => {
+            $(
+                impl Widen for $t {
+                    type Wide = $wide;
+                    fn widen(self) -> $wide {
+                        self as $wide
+                    }
+                    fn narrow_saturating(wide: $wide) -> $t {
+                        wide.clamp(<$t>::MIN as $wide, <$t>::MAX as $wide) as $t
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_widen!(i8 => i16, i16 => i32, i32 => i64, i64 => i128, isize => i128);
+
+    impl<T: Integer> Matrix<T> {
+        pub fn checked_add(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.checked_elementwise(other, "addition", T::checked_add)
+        }
+
+        pub fn checked_subtract(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.checked_elementwise(other, "subtraction", T::checked_sub)
+        }
+
+        pub fn checked_multiply(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.check_product(other)?;
+
+            let mut data = Vec::with_capacity(self.rows * other.cols);
+            for i in 0..self.rows {
+                for j in 0..other.cols {
+                    let mut sum = T::zero();
+                    for k in 0..self.cols {
+                        sum = self.data[i * self.cols + k]
+                            .checked_mul(other.data[k * other.cols + j])
+                            .and_then(|product| sum.checked_add(product))
+                            .ok_or(MatrixError::Overflow { op: "multiplication", row: i, col: j })?;
+                    }
+                    data.push(sum);
+                }
+            }
+
+            Ok(Matrix { rows: self.rows, cols: other.cols, data })
+        }
+
+        pub fn checked_scalar_multiply(&self, scalar: T) -> Result<Matrix<T>, MatrixError> {
+            let mut data = Vec::with_capacity(self.data.len());
+            for (index, &a) in self.data.iter().enumerate() {
+                let cell = a.checked_mul(scalar).ok_or(MatrixError::Overflow {
+                    op: "scalar multiplication",
+                    row: index / self.cols,
+                    col: index % self.cols,
+                })?;
+                data.push(cell);
+            }
+
+            Ok(Matrix { rows: self.rows, cols: self.cols, data })
+        }
+
+        pub fn saturating_add(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.elementwise(other, "addition", T::saturating_add)
+        }
+
+        pub fn saturating_subtract(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.elementwise(other, "subtraction", T::saturating_sub)
+        }
+
+        pub fn saturating_scalar_multiply(&self, scalar: T) -> Matrix<T> {
+            let data = self.data.iter().map(|&a| a.saturating_mul(scalar)).collect();
+            Matrix { rows: self.rows, cols: self.cols, data }
+        }
+
+        pub fn wrapping_add(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.elementwise(other, "addition", T::wrapping_add)
+        }
+
+        pub fn wrapping_subtract(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.elementwise(other, "subtraction", T::wrapping_sub)
+        }
+
+        pub fn wrapping_multiply(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.multiply_with(other, |sum, a, b| sum.wrapping_add(a.wrapping_mul(b)))
+        }
+
+        pub fn wrapping_scalar_multiply(&self, scalar: T) -> Matrix<T> {
+            let data = self.data.iter().map(|&a| a.wrapping_mul(scalar)).collect();
+            Matrix { rows: self.rows, cols: self.cols, data }
+        }
+
+        fn elementwise(&self, other: &Matrix<T>, op: &'static str, f: fn(T, T) -> T) -> Result<Matrix<T>, MatrixError> {
+            self.check_same_shape(other, op)?;
+
+            let data = self.data.iter().zip(&other.data).map(|(&a, &b)| f(a, b)).collect();
+            Ok(Matrix { rows: self.rows, cols: self.cols, data })
+        }
+
+        fn checked_elementwise(&self, other: &Matrix<T>, op: &'static str, f: fn(T, T) -> Option<T>) -> Result<Matrix<T>, MatrixError> {
+            self.check_same_shape(other, op)?;
+
+            let mut data = Vec::with_capacity(self.data.len());
+            for (index, (&a, &b)) in self.data.iter().zip(&other.data).enumerate() {
+                let cell = f(a, b).ok_or(MatrixError::Overflow { op, row: index / self.cols, col: index % self.cols })?;
+                data.push(cell);
+            }
+
+            Ok(Matrix { rows: self.rows, cols: self.cols, data })
+        }
+
+        fn multiply_with(&self, other: &Matrix<T>, step: impl Fn(T, T, T) -> T) -> Result<Matrix<T>, MatrixError> {
+            self.check_product(other)?;
+
+            let mut data = Vec::with_capacity(self.rows * other.cols);
+            for i in 0..self.rows {
+                for j in 0..other.cols {
+                    let sum = (0..self.cols).fold(T::zero(), |sum, k| {
+                        step(sum, self.data[i * self.cols + k], other.data[k * other.cols + j])
+                    });
+                    data.push(sum);
+                }
+            }
+
+            Ok(Matrix { rows: self.rows, cols: other.cols, data })
+        }
+    }
+
+    // Widening mode: convert to the wider type first, then use checked arithmetic
+    // there, so i32 CSV data multiplies exactly in i64 and i64 data in i128.
+    impl<T: Widen> Matrix<T> {
+        pub fn widen(&self) -> Matrix<T::Wide> {
+            Matrix { rows: self.rows, cols: self.cols, data: self.data.iter().map(|&a| a.widen()).collect() }
+        }
+
+        pub fn widening_add(&self, other: &Matrix<T>) -> Result<Matrix<T::Wide>, MatrixError> {
+            self.widen().checked_add(&other.widen())
+        }
+
+        pub fn widening_subtract(&self, other: &Matrix<T>) -> Result<Matrix<T::Wide>, MatrixError> {
+            self.widen().checked_subtract(&other.widen())
+        }
+
+        pub fn widening_multiply(&self, other: &Matrix<T>) -> Result<Matrix<T::Wide>, MatrixError> {
+            self.widen().checked_multiply(&other.widen())
+        }
+
+        pub fn widening_scalar_multiply(&self, scalar: T) -> Result<Matrix<T::Wide>, MatrixError> {
+            self.widen().checked_scalar_multiply(scalar.widen())
+        }
+
+        // Exact products in the wide type, summed while counting how often the
+        // sum wraps around, so each cell is the exact dot product clamped once
+        // and does not depend on the order of the terms
+        pub fn saturating_multiply(&self, other: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            self.check_product(other)?;
+
+            let mut data = Vec::with_capacity(self.rows * other.cols);
+            for i in 0..self.rows {
+                for j in 0..other.cols {
+                    let (mut sum, mut wraps) = (T::Wide::zero(), 0i64);
+                    for k in 0..self.cols {
+                        let term = self.data[i * self.cols + k].widen().wrapping_mul(other.data[k * other.cols + j].widen());
+                        if sum.checked_add(term).is_none() {
+                            wraps += if term > T::Wide::zero() { 1 } else { -1 };
+                        }
+                        sum = sum.wrapping_add(term);
+                    }
+                    data.push(match wraps.cmp(&0) {
+                        std::cmp::Ordering::Greater => T::max_value(),
+                        std::cmp::Ordering::Less => T::min_value(),
+                        std::cmp::Ordering::Equal => T::narrow_saturating(sum),
+                    });
+                }
+            }
+
+            Ok(Matrix { rows: self.rows, cols: other.cols, data })
+        }
+    }
+
+    // i128 has no wider primitive, so its products are summed exactly as
+    // 256-bit values `high·2¹²⁸ + low` and clamped once, like the `Widen` version
+    impl Matrix<i128> {
+        pub fn saturating_multiply(&self, other: &Matrix<i128>) -> Result<Matrix<i128>, MatrixError> {
+            self.check_product(other)?;
+
+            let mut data = Vec::with_capacity(self.rows * other.cols);
+            for i in 0..self.rows {
+                for j in 0..other.cols {
+                    let (mut high, mut low, mut wraps) = (0i128, 0u128, 0i64);
+                    for k in 0..self.cols {
+                        let (term_high, term_low) = full_product(self.data[i * self.cols + k], other.data[k * other.cols + j]);
+                        let (sum_low, carry) = low.overflowing_add(term_low);
+                        let step = term_high + carry as i128;
+                        if high.checked_add(step).is_none() {
+                            wraps += if step > 0 { 1 } else { -1 };
+                        }
+                        high = high.wrapping_add(step);
+                        low = sum_low;
+                    }
+                    data.push(match (wraps.cmp(&0), high) {
+                        (std::cmp::Ordering::Greater, _) => i128::MAX,
+                        (std::cmp::Ordering::Less, _) => i128::MIN,
+                        (_, 0) if low <= i128::MAX as u128 => low as i128,
+                        (_, -1) if low > i128::MAX as u128 => low as i128,
+                        (_, high) => if high < 0 { i128::MIN } else { i128::MAX },
+                    });
+                }
+            }
+
+            Ok(Matrix { rows: self.rows, cols: other.cols, data })
+        }
+    }
+
+    // The exact product a·b as a two's complement 256-bit value (high, low)
+    fn full_product(a: i128, b: i128) -> (i128, u128) {
+        const MASK: u128 = u64::MAX as u128;
+        let (x, y) = (a.unsigned_abs(), b.unsigned_abs());
+        let (x1, x0, y1, y0) = (x >> 64, x & MASK, y >> 64, y & MASK);
+        let (p00, p01, p10, p11) = (x0 * y0, x0 * y1, x1 * y0, x1 * y1);
+        let middle = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+        let low = (p00 & MASK) | (middle << 64);
+        let high = p11 + (p01 >> 64) + (p10 >> 64) + (middle >> 64);
+
+        if (a < 0) != (b < 0) {
+            let low = (!low).wrapping_add(1);
+            let high = (!high).wrapping_add((low == 0) as u128);
+            (high as i128, low)
+        } else {
+            (high as i128, low)
+        }
+    }
+
+    // Floating-point element types.
+    pub trait Real: Numeric + Copy + PartialOrd {
+        fn divide(&self, other: &Self) -> Self;
+        fn abs(&self) -> Self;
+        fn sqrt(&self) -> Self;
+        fn epsilon() -> Self;
+        fn from_f64(value: f64) -> Self;
+        fn to_f64(&self) -> f64;
+    }
+
+    macro_rules! impl_real {
+        ($($t:ident),*) => {
+            $(
+                impl Real for $t {
+                    fn divide(&self, other: &Self) -> Self {
+                        self / other
+                    }
+
+                    fn abs(&self) -> Self {
+                        $t::abs(*self)
+                    }
+
+                    fn sqrt(&self) -> Self {
+                        $t::sqrt(*self)
+                    }
+
+                    fn epsilon() -> Self {
+                        $t::EPSILON
+                    }
+
+                    fn from_f64(value: f64) -> Self {
+                        value as $t
+                    }
+
+                    fn to_f64(&self) -> f64 {
+                        *self as f64
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_real!(f32, f64);
+
+    // Element types with an elimination scheme for `determinant` and `rank`:
+    // fraction-free Bareiss for integers, so results are exact, partial
+    // pivoting LU for floats and plain Gaussian elimination for rationals.
+    pub trait Elimination: Numeric {
+        fn determinant(matrix: &Matrix<Self>) -> Result<Self, MatrixError>;
+        fn rank(matrix: &Matrix<Self>) -> Result<usize, MatrixError>;
+    }
+
+    macro_rules! impl_elimination {
+        ($determinant:ident, $rank:ident: $($t:ty),*) => {
+            $(
+                impl Elimination for $t {
+                    fn determinant(matrix: &Matrix<Self>) -> Result<Self, MatrixError> {
+                        $determinant(matrix)
+                    }
+
+                    fn rank(matrix: &Matrix<Self>) -> Result<usize, MatrixError> {
+                        $rank(matrix)
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_elimination!(bareiss_determinant, bareiss_rank: i8, i16, i32, i64, i128, isize);
+    impl_elimination!(lu_determinant, lu_rank: f32, f64);
+    impl_elimination!(rational_determinant, rational_rank: Rational);
+
+    impl<T: Elimination> Matrix<T> {
+        pub fn determinant(&self) -> Result<T, MatrixError> {
+            T::determinant(self)
+        }
+
+        pub fn rank(&self) -> Result<usize, MatrixError> {
+            T::rank(self)
+        }
+    }
+
+    // Fraction-free elimination. Every division is exact, and each pivot is a
+    // leading minor of the input, so the last pivot of a full-rank square
+    // matrix is its determinant. Returns the reduced matrix, its rank and
+    // whether an odd number of row swaps was made.
+    fn bareiss<T: Integer>(matrix: &Matrix<T>, op: &'static str) -> Result<(Matrix<T>, usize, bool), MatrixError> {
+        let mut a = matrix.clone();
+        let mut previous = T::one();
+        let mut rank = 0;
+        let mut swapped = false;
+
+        for col in 0..a.cols {
+            if rank == a.rows {
+                break;
+            }
+            let pivot_row = match (rank..a.rows).find(|&i| a[(i, col)] != T::zero()) {
+                Some(row) => row,
+                None => continue,
+            };
+            if pivot_row != rank {
+                a.swap_rows(pivot_row, rank);
+                swapped = !swapped;
+            }
+
+            let pivot = a[(rank, col)];
+            for i in rank + 1..a.rows {
+                for j in col + 1..a.cols {
+                    a[(i, j)] = a[(i, j)]
+                        .checked_mul(pivot)
+                        .zip(a[(i, col)].checked_mul(a[(rank, j)]))
+                        .and_then(|(x, y)| x.checked_sub(y))
+                        .and_then(|numerator| numerator.checked_div(previous))
+                        .ok_or(MatrixError::Overflow { op, row: i, col: j })?;
+                }
+                a[(i, col)] = T::zero();
+            }
+            previous = pivot;
+            rank += 1;
+        }
+
+        Ok((a, rank, swapped))
+    }
+
+    fn bareiss_determinant<T: Integer>(matrix: &Matrix<T>) -> Result<T, MatrixError> {
+        matrix.check_square()?;
+        let (reduced, rank, swapped) = bareiss(matrix, "determinant")?;
+        let n = matrix.rows;
+        if rank < n {
+            return Ok(T::zero());
+        }
+
+        let last = reduced[(n - 1, n - 1)];
+        if swapped {
+            T::zero().checked_sub(last).ok_or(MatrixError::Overflow { op: "determinant", row: n - 1, col: n - 1 })
+        } else {
+            Ok(last)
+        }
+    }
+
+    fn bareiss_rank<T: Integer>(matrix: &Matrix<T>) -> Result<usize, MatrixError> {
+        Ok(bareiss(matrix, "rank")?.1)
+    }
+
+    fn lu_determinant<T: Real>(matrix: &Matrix<T>) -> Result<T, MatrixError> {
+        matrix.check_square()?;
+        let mut a = matrix.clone();
+        let n = a.rows;
+        let mut determinant = T::one();
+
+        for k in 0..n {
+            let pivot_row = (k..n).fold(k, |best, i| if a[(i, k)].abs() > a[(best, k)].abs() { i } else { best });
+            if a[(pivot_row, k)] == T::zero() {
+                return Ok(T::zero());
+            }
+            if pivot_row != k {
+                a.swap_rows(pivot_row, k);
+                determinant = T::zero().subtract(&determinant);
+            }
+
+            let pivot = a[(k, k)];
+            determinant = determinant.multiply(&pivot);
+            for i in k + 1..n {
+                let factor = a[(i, k)].divide(&pivot);
+                for j in k + 1..n {
+                    a[(i, j)] = a[(i, j)].subtract(&factor.multiply(&a[(k, j)]));
+                }
+            }
+        }
+
+        Ok(determinant)
+    }
+
+    // Gaussian elimination with partial pivoting; entries within the
+    // matrix's default tolerance of zero count as zero.
+    fn lu_rank<T: Real>(matrix: &Matrix<T>) -> Result<usize, MatrixError> {
+        let mut a = matrix.clone();
+        let tolerance = a.tolerance();
+        let mut rank = 0;
+
+        for col in 0..a.cols {
+            if rank == a.rows {
+                break;
+            }
+            let pivot_row = (rank..a.rows).fold(rank, |best, i| if a[(i, col)].abs() > a[(best, col)].abs() { i } else { best });
+            if a[(pivot_row, col)].abs() <= tolerance {
+                continue;
+            }
+            a.swap_rows(pivot_row, rank);
+
+            let pivot = a[(rank, col)];
+            for i in rank + 1..a.rows {
+                let factor = a[(i, col)].divide(&pivot);
+                for j in col..a.cols {
+                    a[(i, j)] = a[(i, j)].subtract(&factor.multiply(&a[(rank, j)]));
+                }
+            }
+            rank += 1;
+        }
+
+        Ok(rank)
+    }
+
+    // PA = LU, where row i of PA is row `permutation[i]` of A. L is unit lower
+    // triangular; a singular A still factors, with a zero on U's diagonal.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct LuDecomposition<T> {
+        pub lower: Matrix<T>,
+        pub upper: Matrix<T>,
+        pub permutation: Vec<usize>,
+    }
+
+    impl<T: Real> LuDecomposition<T> {
+        pub fn permutation_matrix(&self) -> Matrix<T> {
+            let n = self.permutation.len();
+            let mut p = Matrix::new(n, n);
+            for (i, &row) in self.permutation.iter().enumerate() {
+                p[(i, row)] = T::one();
+            }
+            p
+        }
+
+        // Solves Ax = b by substitution, so one factorization serves any number
+        // of right-hand sides
+        pub fn solve(&self, b: &[T]) -> Result<Vec<T>, MatrixError> {
+            let n = self.permutation.len();
+            if b.len() != n {
+                return Err(MatrixError::DimensionMismatch { op: "solve", left: (n, n), right: (b.len(), 1) });
+            }
+            let tolerance = self.upper.tolerance();
+            if (0..n).any(|i| self.upper[(i, i)].abs() <= tolerance) {
+                return Err(MatrixError::Singular);
+            }
+
+            // Ly = Pb, then Ux = y
+            let mut y: Vec<T> = Vec::with_capacity(n);
+            for i in 0..n {
+                let sum = (0..i).fold(b[self.permutation[i]], |sum, j| sum.subtract(&self.lower[(i, j)].multiply(&y[j])));
+                y.push(sum);
+            }
+            Ok(back_substitute(&self.upper, &y))
+        }
+    }
+
+    // A = QR with Q orthogonal (rows × rows) and R upper triangular (rows × cols)
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct QrDecomposition<T> {
+        pub q: Matrix<T>,
+        pub r: Matrix<T>,
+    }
+
+    impl<T: Real> QrDecomposition<T> {
+        // Minimizes ‖Ax - b‖ by solving Rx = Qᵀb over the leading rows; A needs
+        // full column rank
+        pub fn least_squares(&self, b: &[T]) -> Result<Vec<T>, MatrixError> {
+            let (m, n) = (self.q.rows, self.r.cols);
+            if b.len() != m {
+                return Err(MatrixError::DimensionMismatch { op: "least squares", left: (m, n), right: (b.len(), 1) });
+            }
+            let tolerance = self.r.tolerance();
+            if m < n || (0..n).any(|i| self.r[(i, i)].abs() <= tolerance) {
+                return Err(MatrixError::Singular);
+            }
+
+            let qt_b: Vec<T> = (0..n)
+                .map(|j| (0..m).fold(T::zero(), |sum, i| sum.add(&self.q[(i, j)].multiply(&b[i]))))
+                .collect();
+            Ok(back_substitute(&self.r, &qt_b))
+        }
+    }
+
+    // Solves the leading y.len() × y.len() block of upper triangular `upper`
+    fn back_substitute<T: Real>(upper: &Matrix<T>, y: &[T]) -> Vec<T> {
+        let n = y.len();
+        let mut x = vec![T::zero(); n];
+        for i in (0..n).rev() {
+            let sum = (i + 1..n).fold(y[i], |sum, j| sum.subtract(&upper[(i, j)].multiply(&x[j])));
+            x[i] = sum.divide(&upper[(i, i)]);
+        }
+        x
+    }
+
+    // A solution together with how far it is from exact. How much input error
+    // it may amplify is `Matrix::condition_number`, left out here because it
+    // needs a full SVD on top of the factorization.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SolveReport<T> {
+        pub x: Vec<T>,
+        // ‖Ax - b‖₂
+        pub residual_norm: T,
+    }
+
+    impl<T: Real> Matrix<T> {
+        // max(rows, cols) · ε · (largest absolute entry): differences below
+        // this are indistinguishable from round-off
+        pub fn tolerance(&self) -> T {
+            let largest = self.data.iter().fold(T::zero(), |max, x| if x.abs() > max { x.abs() } else { max });
+            T::from_f64(self.rows.max(self.cols) as f64).multiply(&T::epsilon()).multiply(&largest)
+        }
+
+        pub fn approx_eq(&self, other: &Matrix<T>, tolerance: T) -> bool {
+            self.rows == other.rows
+                && self.cols == other.cols
+                && self.data.iter().zip(&other.data).all(|(a, b)| a.subtract(b).abs() <= tolerance)
+        }
+
+        pub fn lu(&self) -> Result<LuDecomposition<T>, MatrixError> {
+            self.check_square()?;
+            let n = self.rows;
+            let mut upper = self.clone();
+            let mut lower = Matrix::identity(n);
+            let mut permutation: Vec<usize> = (0..n).collect();
+
+            for k in 0..n {
+                let pivot_row = (k..n).fold(k, |best, i| if upper[(i, k)].abs() > upper[(best, k)].abs() { i } else { best });
+                if upper[(pivot_row, k)] == T::zero() {
+                    continue;
+                }
+                if pivot_row != k {
+                    upper.swap_rows(pivot_row, k);
+                    permutation.swap(pivot_row, k);
+                    // Multipliers found so far move with their rows
+                    for j in 0..k {
+                        lower.data.swap(pivot_row * n + j, k * n + j);
+                    }
+                }
+
+                let pivot = upper[(k, k)];
+                for i in k + 1..n {
+                    let factor = upper[(i, k)].divide(&pivot);
+                    lower[(i, k)] = factor;
+                    upper[(i, k)] = T::zero();
+                    for j in k + 1..n {
+                        upper[(i, j)] = upper[(i, j)].subtract(&factor.multiply(&upper[(k, j)]));
+                    }
+                }
+            }
+
+            Ok(LuDecomposition { lower, upper, permutation })
+        }
+
+        // Householder reflections H = I - 2vvᵀ/(vᵀv), each zeroing one column below the diagonal
+        pub fn qr(&self) -> Result<QrDecomposition<T>, MatrixError> {
+            if self.is_empty() {
+                return Err(MatrixError::Empty);
+            }
+            let (m, n) = (self.rows, self.cols);
+            let two = T::from_f64(2.0);
+            let mut r = self.clone();
+            let mut q: Matrix<T> = Matrix::identity(m);
+
+            for k in 0..n.min(m - 1) {
+                let norm = (k..m).fold(T::zero(), |sum, i| sum.add(&r[(i, k)].multiply(&r[(i, k)]))).sqrt();
+                if norm == T::zero() {
+                    continue;
+                }
+                // Reflect onto -sign(x₀)‖x‖e₁ so that v₀ does not cancel
+                let alpha = if r[(k, k)] > T::zero() { T::zero().subtract(&norm) } else { norm };
+                let mut v: Vec<T> = (k..m).map(|i| r[(i, k)]).collect();
+                v[0] = v[0].subtract(&alpha);
+                let v_norm_squared = v.iter().fold(T::zero(), |sum, x| sum.add(&x.multiply(x)));
+
+                for j in k..n {
+                    let dot = (k..m).fold(T::zero(), |sum, i| sum.add(&v[i - k].multiply(&r[(i, j)])));
+                    let scale = two.multiply(&dot).divide(&v_norm_squared);
+                    for i in k..m {
+                        r[(i, j)] = r[(i, j)].subtract(&scale.multiply(&v[i - k]));
+                    }
+                }
+                for i in k + 1..m {
+                    r[(i, k)] = T::zero();
+                }
+
+                // Q ← QH
+                for row in 0..m {
+                    let dot = (k..m).fold(T::zero(), |sum, j| sum.add(&q[(row, j)].multiply(&v[j - k])));
+                    let scale = two.multiply(&dot).divide(&v_norm_squared);
+                    for j in k..m {
+                        q[(row, j)] = q[(row, j)].subtract(&scale.multiply(&v[j - k]));
+                    }
+                }
+            }
+
+            Ok(QrDecomposition { q, r })
+        }
+
+        // Lower triangular L with A = LLᵀ, for symmetric positive definite A
+        pub fn cholesky(&self) -> Result<Matrix<T>, MatrixError> {
+            self.check_square()?;
+            let n = self.rows;
+            let tolerance = self.tolerance();
+            for i in 0..n {
+                for j in i + 1..n {
+                    if self[(i, j)].subtract(&self[(j, i)]).abs() > tolerance {
+                        return Err(MatrixError::NotSymmetric);
+                    }
+                }
+            }
+
+            let mut lower: Matrix<T> = Matrix::new(n, n);
+            for j in 0..n {
+                let diagonal = (0..j).fold(self[(j, j)], |sum, k| sum.subtract(&lower[(j, k)].multiply(&lower[(j, k)])));
+                if diagonal <= T::zero() {
+                    return Err(MatrixError::NotPositiveDefinite);
+                }
+                let diagonal = diagonal.sqrt();
+                lower[(j, j)] = diagonal;
+                for i in j + 1..n {
+                    let sum = (0..j).fold(self[(i, j)], |sum, k| sum.subtract(&lower[(i, k)].multiply(&lower[(j, k)])));
+                    lower[(i, j)] = sum.divide(&diagonal);
+                }
+            }
+
+            Ok(lower)
+        }
+    }
+
+    // A = V diag(values) Vᵀ with eigenvalues in descending order and the
+    // matching orthonormal eigenvectors as the columns of `vectors`
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SymmetricEigen<T> {
+        pub values: Vec<T>,
+        pub vectors: Matrix<T>,
+    }
+
+    // Thin SVD A = U diag(singular_values) Vᵀ: with k = min(rows, cols), U is
+    // rows × k, V is cols × k and the singular values descend. Columns of U
+    // paired with a zero singular value are zero.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Svd<T> {
+        pub u: Matrix<T>,
+        pub singular_values: Vec<T>,
+        pub v: Matrix<T>,
+    }
+
+    const MAX_SWEEPS: usize = 100;
+
+    // Rotation (c, s) that zeroes the off-diagonal entry of the 2 × 2 symmetric
+    // matrix [[app, apq], [apq, aqq]], taking the smaller of the two angles
+    fn jacobi_rotation<T: Real>(app: T, aqq: T, apq: T) -> (T, T) {
+        let two = T::from_f64(2.0);
+        let theta = aqq.subtract(&app).divide(&two.multiply(&apq));
+        let root = theta.multiply(&theta).add(&T::one()).sqrt();
+        let t = T::one().divide(&theta.abs().add(&root));
+        let t = if theta < T::zero() { T::zero().subtract(&t) } else { t };
+        let c = T::one().divide(&t.multiply(&t).add(&T::one()).sqrt());
+        (c, t.multiply(&c))
+    }
+
+    impl<T: Real> Matrix<T> {
+        // Cyclic Jacobi: rotate away each off-diagonal entry in turn until the
+        // off-diagonal part is negligible
+        pub fn symmetric_eigen(&self) -> Result<SymmetricEigen<T>, MatrixError> {
+            self.check_square()?;
+            let n = self.rows;
+            let tolerance = self.tolerance();
+            for i in 0..n {
+                for j in i + 1..n {
+                    if self[(i, j)].subtract(&self[(j, i)]).abs() > tolerance {
+                        return Err(MatrixError::NotSymmetric);
+                    }
+                }
+            }
+
+            let mut a = self.clone();
+            let mut vectors: Matrix<T> = Matrix::identity(n);
+            let norm = self.data.iter().fold(T::zero(), |sum, x| sum.add(&x.multiply(x))).sqrt();
+            let threshold = T::epsilon().multiply(&norm);
+            let mut converged = false;
+
+            for _ in 0..MAX_SWEEPS {
+                let off = (0..n)
+                    .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                    .fold(T::zero(), |sum, (i, j)| sum.add(&a[(i, j)].multiply(&a[(i, j)])))
+                    .sqrt();
+                if off <= threshold {
+                    converged = true;
+                    break;
+                }
+
+                for p in 0..n {
+                    for q in p + 1..n {
+                        if a[(p, q)] == T::zero() {
+                            continue;
+                        }
+                        let (c, s) = jacobi_rotation(a[(p, p)], a[(q, q)], a[(p, q)]);
+                        a.rotate_columns(p, q, c, s);
+                        a.rotate_rows(p, q, c, s);
+                        vectors.rotate_columns(p, q, c, s);
+                        a[(p, q)] = T::zero();
+                        a[(q, p)] = T::zero();
+                    }
+                }
+            }
+            if !converged {
+                return Err(MatrixError::NoConvergence { op: "symmetric eigen-decomposition" });
+            }
+
+            let mut order: Vec<usize> = (0..n).collect();
+            order.sort_by(|&i, &j| a[(j, j)].partial_cmp(&a[(i, i)]).unwrap_or(std::cmp::Ordering::Equal));
+            let values = order.iter().map(|&i| a[(i, i)]).collect();
+            Ok(SymmetricEigen { values, vectors: vectors.select_columns(&order) })
+        }
+
+        // All eigenvalues of a square matrix as (real, imaginary) pairs, complex
+        // ones in conjugate pairs, sorted by descending real part and then
+        // imaginary part. The matrix is reduced to Hessenberg form and then
+        // iterated with Francis double-shift QR, in f64 throughout.
+        pub fn eigenvalues(&self) -> Result<Vec<(f64, f64)>, MatrixError> {
+            self.check_square()?;
+            let mut a = self.map(|x| x.to_f64()).hessenberg();
+            let mut values = francis_qr(&mut a)?;
+            values.sort_by(|x, y| y.0.partial_cmp(&x.0).unwrap_or(std::cmp::Ordering::Equal).then(y.1.partial_cmp(&x.1).unwrap_or(std::cmp::Ordering::Equal)));
+            Ok(values)
+        }
+
+        // One-sided Jacobi (Hestenes): rotate pairs of columns until all are
+        // mutually orthogonal; their norms are then the singular values
+        pub fn svd(&self) -> Result<Svd<T>, MatrixError> {
+            if self.is_empty() {
+                return Err(MatrixError::Empty);
+            }
+            // Aᵀ = UΣVᵀ gives A = VΣUᵀ, so wide matrices reuse the tall case
+            if self.rows < self.cols {
+                let Svd { u, singular_values, v } = self.transpose().svd()?;
+                return Ok(Svd { u: v, singular_values, v: u });
+            }
+
+            let n = self.cols;
+            let mut u = self.clone();
+            let mut v: Matrix<T> = Matrix::identity(n);
+            let mut converged = false;
+
+            for _ in 0..MAX_SWEEPS {
+                let mut rotated = false;
+                for p in 0..n {
+                    for q in p + 1..n {
+                        let (alpha, beta, gamma) = (0..u.rows).fold((T::zero(), T::zero(), T::zero()), |(a, b, g), i| {
+                            let (x, y) = (u[(i, p)], u[(i, q)]);
+                            (a.add(&x.multiply(&x)), b.add(&y.multiply(&y)), g.add(&x.multiply(&y)))
+                        });
+                        if gamma.abs() <= T::epsilon().multiply(&alpha.multiply(&beta).sqrt()) {
+                            continue;
+                        }
+                        rotated = true;
+                        let (c, s) = jacobi_rotation(alpha, beta, gamma);
+                        u.rotate_columns(p, q, c, s);
+                        v.rotate_columns(p, q, c, s);
+                    }
+                }
+                if !rotated {
+                    converged = true;
+                    break;
+                }
+            }
+            if !converged {
+                return Err(MatrixError::NoConvergence { op: "singular value decomposition" });
+            }
+
+            let norms: Vec<T> = (0..n)
+                .map(|j| (0..u.rows).fold(T::zero(), |sum, i| sum.add(&u[(i, j)].multiply(&u[(i, j)]))).sqrt())
+                .collect();
+            for (j, norm) in norms.iter().enumerate() {
+                if *norm > T::zero() {
+                    for i in 0..u.rows {
+                        u[(i, j)] = u[(i, j)].divide(norm);
+                    }
+                }
+            }
+
+            let mut order: Vec<usize> = (0..n).collect();
+            order.sort_by(|&i, &j| norms[j].partial_cmp(&norms[i]).unwrap_or(std::cmp::Ordering::Equal));
+            Ok(Svd {
+                u: u.select_columns(&order),
+                singular_values: order.iter().map(|&j| norms[j]).collect(),
+                v: v.select_columns(&order),
+            })
+        }
+
+        // Square systems through LU with partial pivoting
+        pub fn solve(&self, b: &[T]) -> Result<SolveReport<T>, MatrixError> {
+            let x = self.lu()?.solve(b)?;
+            Ok(self.report(x, b))
+        }
+
+        // Overdetermined systems through Householder QR
+        pub fn least_squares(&self, b: &[T]) -> Result<SolveReport<T>, MatrixError> {
+            let x = self.qr()?.least_squares(b)?;
+            Ok(self.report(x, b))
+        }
+
+        // AX = B for every column of B, factoring A only once
+        pub fn solve_many(&self, right_hand_sides: &Matrix<T>) -> Result<Matrix<T>, MatrixError> {
+            if right_hand_sides.rows != self.rows {
+                return Err(MatrixError::DimensionMismatch {
+                    op: "solve",
+                    left: (self.rows, self.cols),
+                    right: (right_hand_sides.rows, right_hand_sides.cols),
+                });
+            }
+            let lu = self.lu()?;
+            let mut solution = Matrix::new(self.cols, right_hand_sides.cols);
+            for j in 0..right_hand_sides.cols {
+                for (i, value) in lu.solve(&right_hand_sides.column(j))?.into_iter().enumerate() {
+                    solution[(i, j)] = value;
+                }
+            }
+            Ok(solution)
+        }
+
+        fn report(&self, x: Vec<T>, b: &[T]) -> SolveReport<T> {
+            let residual_norm = (0..self.rows)
+                .map(|i| (0..self.cols).fold(T::zero(), |sum, j| sum.add(&self[(i, j)].multiply(&x[j]))).subtract(&b[i]))
+                .fold(T::zero(), |sum, r| sum.add(&r.multiply(&r)))
+                .sqrt();
+            SolveReport { x, residual_norm }
+        }
+
+        // 2-norm condition number σmax / σmin, infinite when σmin is zero; about
+        // log10 of it is the number of digits a solve can lose
+        pub fn condition_number(&self) -> Result<T, MatrixError> {
+            let singular_values = self.svd()?.singular_values;
+            let largest = singular_values[0];
+            let smallest = singular_values[singular_values.len() - 1];
+            if smallest == T::zero() {
+                return Ok(T::from_f64(f64::INFINITY));
+            }
+            Ok(largest.divide(&smallest))
+        }
+
+        // Columns p and q replaced by c·p - s·q and s·p + c·q
+        fn rotate_columns(&mut self, p: usize, q: usize, c: T, s: T) {
+            for i in 0..self.rows {
+                let (x, y) = (self[(i, p)], self[(i, q)]);
+                self[(i, p)] = c.multiply(&x).subtract(&s.multiply(&y));
+                self[(i, q)] = s.multiply(&x).add(&c.multiply(&y));
+            }
+        }
+
+        fn rotate_rows(&mut self, p: usize, q: usize, c: T, s: T) {
+            for j in 0..self.cols {
+                let (x, y) = (self[(p, j)], self[(q, j)]);
+                self[(p, j)] = c.multiply(&x).subtract(&s.multiply(&y));
+                self[(q, j)] = s.multiply(&x).add(&c.multiply(&y));
+            }
+        }
+    }
+
+    impl Matrix<f64> {
+        // Similar upper Hessenberg matrix, by Householder reflections applied on both sides
+        fn hessenberg(&self) -> Matrix<f64> {
+            let n = self.rows;
+            let mut a = self.clone();
+            for k in 0..n.saturating_sub(2) {
+                let norm = (k + 1..n).map(|i| a[(i, k)] * a[(i, k)]).sum::<f64>().sqrt();
+                if norm == 0.0 {
+                    continue;
+                }
+                let alpha = if a[(k + 1, k)] > 0.0 { -norm } else { norm };
+                let mut v: Vec<f64> = (k + 1..n).map(|i| a[(i, k)]).collect();
+                v[0] -= alpha;
+                let v_norm_squared: f64 = v.iter().map(|x| x * x).sum();
+
+                for j in 0..n {
+                    let scale = 2.0 * (k + 1..n).map(|i| v[i - k - 1] * a[(i, j)]).sum::<f64>() / v_norm_squared;
+                    for i in k + 1..n {
+                        a[(i, j)] -= scale * v[i - k - 1];
+                    }
+                }
+                for i in 0..n {
+                    let scale = 2.0 * (k + 1..n).map(|j| a[(i, j)] * v[j - k - 1]).sum::<f64>() / v_norm_squared;
+                    for j in k + 1..n {
+                        a[(i, j)] -= scale * v[j - k - 1];
+                    }
+                }
+                for i in k + 2..n {
+                    a[(i, k)] = 0.0;
+                }
+            }
+            a
+        }
+    }
+
+    // Eigenvalues (real, imaginary) of an upper Hessenberg matrix, which is
+    // overwritten. Small subdiagonal entries split off 1 × 1 and 2 × 2 blocks
+    // from the bottom; otherwise a double-shift QR step is applied.
+    fn francis_qr(a: &mut Matrix<f64>) -> Result<Vec<(f64, f64)>, MatrixError> {
+        let n = a.rows;
+        let mut values = Vec::with_capacity(n);
+        let norm: f64 = (0..n).flat_map(|i| (i.saturating_sub(1)..n).map(move |j| (i, j))).map(|(i, j)| a[(i, j)].abs()).sum();
+        let mut shift = 0.0;
+        let mut end = n;
+
+        while end > 0 {
+            let last = end - 1;
+            let mut iterations = 0;
+            loop {
+                // Lowest l with a negligible subdiagonal entry a[l][l - 1]
+                let mut l = last;
+                while l > 0 {
+                    let scale = a[(l - 1, l - 1)].abs() + a[(l, l)].abs();
+                    let scale = if scale == 0.0 { norm } else { scale };
+                    if a[(l, l - 1)].abs() + scale == scale {
+                        a[(l, l - 1)] = 0.0;
+                        break;
+                    }
+                    l -= 1;
+                }
+
+                let mut x = a[(last, last)];
+                if l == last {
+                    values.push((x + shift, 0.0));
+                    end -= 1;
+                    break;
+                }
+                let mut y = a[(last - 1, last - 1)];
+                let mut w = a[(last, last - 1)] * a[(last - 1, last)];
+                if l == last - 1 {
+                    let p = 0.5 * (y - x);
+                    let q = p * p + w;
+                    let z = q.abs().sqrt();
+                    x += shift;
+                    if q >= 0.0 {
+                        let z = p + z.copysign(p);
+                        let second = if z != 0.0 { x - w / z } else { x + z };
+                        values.push((x + z, 0.0));
+                        values.push((second, 0.0));
+                    } else {
+                        values.push((x + p, z));
+                        values.push((x + p, -z));
+                    }
+                    end -= 2;
+                    break;
+                }
+
+                if iterations == 60 {
+                    return Err(MatrixError::NoConvergence { op: "eigenvalue iteration" });
+                }
+                // Exceptional shifts break cycles that ordinary shifts can fall into
+                if iterations == 10 || iterations == 20 {
+                    shift += x;
+                    for i in 0..end {
+                        a[(i, i)] -= x;
+                    }
+                    let s = a[(last, last - 1)].abs() + a[(last - 1, last - 2)].abs();
+                    x = 0.75 * s;
+                    y = x;
+                    w = -0.4375 * s * s;
+                }
+                iterations += 1;
+
+                // Start the bulge at the lowest m where two consecutive subdiagonal entries are small
+                let mut m = last - 2;
+                let (mut p, mut q, mut r);
+                loop {
+                    let z = a[(m, m)];
+                    let rr = x - z;
+                    let ss = y - z;
+                    p = (rr * ss - w) / a[(m + 1, m)] + a[(m, m + 1)];
+                    q = a[(m + 1, m + 1)] - z - rr - ss;
+                    r = a[(m + 2, m + 1)];
+                    let scale = p.abs() + q.abs() + r.abs();
+                    p /= scale;
+                    q /= scale;
+                    r /= scale;
+                    if m == l {
+                        break;
+                    }
+                    let u = a[(m, m - 1)].abs() * (q.abs() + r.abs());
+                    let v = p.abs() * (a[(m - 1, m - 1)].abs() + z.abs() + a[(m + 1, m + 1)].abs());
+                    if u + v == v {
+                        break;
+                    }
+                    m -= 1;
+                }
+                for i in m + 2..end {
+                    a[(i, i - 2)] = 0.0;
+                    if i != m + 2 {
+                        a[(i, i - 3)] = 0.0;
+                    }
+                }
+
+                let mut scale = 0.0;
+                for k in m..last {
+                    if k != m {
+                        p = a[(k, k - 1)];
+                        q = a[(k + 1, k - 1)];
+                        r = if k != last - 1 { a[(k + 2, k - 1)] } else { 0.0 };
+                        scale = p.abs() + q.abs() + r.abs();
+                        if scale != 0.0 {
+                            p /= scale;
+                            q /= scale;
+                            r /= scale;
+                        }
+                    }
+                    let s = (p * p + q * q + r * r).sqrt().copysign(p);
+                    if s == 0.0 {
+                        continue;
+                    }
+                    if k == m {
+                        if l != m {
+                            a[(k, k - 1)] = -a[(k, k - 1)];
+                        }
+                    } else {
+                        a[(k, k - 1)] = -s * scale;
+                    }
+                    p += s;
+                    let (hx, hy, hz) = (p / s, q / s, r / s);
+                    q /= p;
+                    r /= p;
+                    for j in k..end {
+                        let mut t = a[(k, j)] + q * a[(k + 1, j)];
+                        if k != last - 1 {
+                            t += r * a[(k + 2, j)];
+                            a[(k + 2, j)] -= t * hz;
+                        }
+                        a[(k + 1, j)] -= t * hy;
+                        a[(k, j)] -= t * hx;
+                    }
+                    for i in l..=last.min(k + 3) {
+                        let mut t = hx * a[(i, k)] + hy * a[(i, k + 1)];
+                        if k != last - 1 {
+                            t += hz * a[(i, k + 2)];
+                            a[(i, k + 2)] -= t * r;
+                        }
+                        a[(i, k + 1)] -= t * q;
+                        a[(i, k)] -= t;
+                    }
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn rational_determinant(matrix: &Matrix<Rational>) -> Result<Rational, MatrixError> {
+        matrix.check_square()?;
+        let mut a = matrix.clone();
+        let n = a.rows;
+        let mut determinant = Rational::one();
+
+        for k in 0..n {
+            let pivot_row = match (k..n).find(|&i| !a[(i, k)].is_zero()) {
+                Some(row) => row,
+                None => return Ok(Rational::zero()),
+            };
+            if pivot_row != k {
+                a.swap_rows(pivot_row, k);
+                determinant = determinant.checked_negate().ok_or(MatrixError::Overflow { op: "determinant", row: k, col: k })?;
+            }
+
+            let pivot = a[(k, k)];
+            let overflow = |row, col| MatrixError::Overflow { op: "determinant", row, col };
+            determinant = determinant.checked_multiply(&pivot).ok_or(overflow(k, k))?;
+            for i in k + 1..n {
+                let factor = a[(i, k)].checked_divide(&pivot).ok_or(overflow(i, k))?;
+                for j in k + 1..n {
+                    a[(i, j)] = factor
+                        .checked_multiply(&a[(k, j)])
+                        .and_then(|t| a[(i, j)].checked_subtract(&t))
+                        .ok_or(overflow(i, j))?;
+                }
+            }
+        }
+
+        Ok(determinant)
+    }
+
+    fn rational_rank(matrix: &Matrix<Rational>) -> Result<usize, MatrixError> {
+        Ok(matrix.rref_with_pivots("rank")?.1.len())
+    }
+
+    // Solutions of a consistent system Ax = b. When some variables are free,
+    // every solution is `particular` plus a combination of the `null_space` basis.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Solution {
+        Unique(Vec<Rational>),
+        Infinite { particular: Vec<Rational>, null_space: Vec<Vec<Rational>> },
+    }
+
+    impl Matrix<Rational> {
+        // Fails with `Overflow` if an intermediate entry leaves the i64 range
+        pub fn rref(&self) -> Result<Matrix<Rational>, MatrixError> {
+            Ok(self.rref_with_pivots("rref")?.0)
+        }
+
+        // Same reduction as `rref`, recording each row operation along the way
+        pub fn explain_rref(&self) -> Result<RowReduction, MatrixError> {
+            let mut steps = Vec::new();
+            let (result, pivots) = self.reduce("rref", Some(&mut steps))?;
+            Ok(RowReduction { start: self.clone(), steps, result, pivots })
+        }
+
+        // Reduced row echelon form and the column of each pivot, top to bottom.
+        // `op` names the caller in an `Overflow` error.
+        fn rref_with_pivots(&self, op: &'static str) -> Result<(Matrix<Rational>, Vec<usize>), MatrixError> {
+            self.reduce(op, None)
+        }
+
+        fn reduce(
+            &self,
+            op: &'static str,
+            mut log: Option<&mut Vec<RowStep>>,
+        ) -> Result<(Matrix<Rational>, Vec<usize>), MatrixError> {
+            let mut a = self.clone();
+            let mut pivots = Vec::new();
+            let mut record = |op: RowOp, a: &Matrix<Rational>| {
+                if let Some(steps) = log.as_mut() {
+                    steps.push(RowStep { op, matrix: a.clone() });
+                }
+            };
+
+            for col in 0..a.cols {
+                let rank = pivots.len();
+                if rank == a.rows {
+                    break;
+                }
+                let pivot_row = match (rank..a.rows).find(|&i| !a[(i, col)].is_zero()) {
+                    Some(row) => row,
+                    None => continue,
+                };
+                if pivot_row != rank {
+                    a.swap_rows(pivot_row, rank);
+                    record(RowOp::Swap(rank, pivot_row), &a);
+                }
+
+                let overflow = |row, col| MatrixError::Overflow { op, row, col };
+                let pivot = a[(rank, col)];
+                if pivot != Rational::one() {
+                    let factor = Rational::one().checked_divide(&pivot).ok_or(overflow(rank, col))?;
+                    for j in col..a.cols {
+                        a[(rank, j)] = a[(rank, j)].checked_multiply(&factor).ok_or(overflow(rank, j))?;
+                    }
+                    record(RowOp::Scale { row: rank, factor }, &a);
+                }
+                for i in (0..a.rows).filter(|&i| i != rank) {
+                    let factor = a[(i, col)].checked_negate().ok_or(overflow(i, col))?;
+                    if factor.is_zero() {
+                        continue;
+                    }
+                    for j in col..a.cols {
+                        a[(i, j)] = factor
+                            .checked_multiply(&a[(rank, j)])
+                            .and_then(|t| a[(i, j)].checked_add(&t))
+                            .ok_or(overflow(i, j))?;
+                    }
+                    record(RowOp::AddMultiple { target: i, source: rank, factor }, &a);
+                }
+                pivots.push(col);
+            }
+
+            Ok((a, pivots))
+        }
 
-pub mod matrix  {
-    use std::io::BufRead;
-    use std::error::Error;
-    use std::fs::File;
-    use std::path::Path;
-    use std::io::BufReader;
+        // [self | other], side by side
+        fn augment(&self, other: &Matrix<Rational>) -> Matrix<Rational> {
+            let cols = self.cols + other.cols;
+            let mut data = Vec::with_capacity(self.rows * cols);
+            for i in 0..self.rows {
+                data.extend_from_slice(self.row(i));
+                data.extend_from_slice(other.row(i));
+            }
+            Matrix { rows: self.rows, cols, data }
+        }
 
-    #[derive(Debug, Clone, PartialEq)]
-    pub struct Matrix {
-        pub data: Vec<Vec<i32>>,
-    }
+        pub fn inverse(&self) -> Result<Matrix<Rational>, MatrixError> {
+            self.check_square()?;
+            let n = self.rows;
+            let (reduced, pivots) = self.augment(&Matrix::identity(n)).rref_with_pivots("inverse")?;
+            if pivots.len() < n || pivots[n - 1] != n - 1 {
+                return Err(MatrixError::Singular);
+            }
 
-    impl Matrix {
-        pub fn new(rows: usize, cols: usize) -> Matrix {
-            Matrix {
-                data: vec![vec![0; cols]; rows],
+            let mut data = Vec::with_capacity(n * n);
+            for i in 0..n {
+                data.extend_from_slice(&reduced.row(i)[n..]);
             }
+            Ok(Matrix { rows: n, cols: n, data })
         }
 
-        pub fn add(&self, other: &Matrix) -> Result<Matrix, &'static str> {
-            if self.data.len() != other.data.len() || self.data[0].len() != other.data[0].len() {
-                return Err("Matrices must have the same dimensions for addition.");
+        pub fn solve(&self, b: &[Rational]) -> Result<Solution, MatrixError> {
+            if self.is_empty() {
+                return Err(MatrixError::Empty);
+            }
+            if b.len() != self.rows {
+                return Err(MatrixError::DimensionMismatch { op: "solve", left: (self.rows, self.cols), right: (b.len(), 1) });
             }
 
-            let mut result = Matrix::new(self.data.len(), self.data[0].len());
+            let n = self.cols;
+            let column = Matrix { rows: b.len(), cols: 1, data: b.to_vec() };
+            let (reduced, pivots) = self.augment(&column).rref_with_pivots("solve")?;
+            // A pivot in the right-hand column is a row reading 0 = 1
+            if pivots.last() == Some(&n) {
+                return Err(MatrixError::Inconsistent);
+            }
 
-            for i in 0..self.data.len() {
-                for j in 0..self.data[0].len() {
-                    result.data[i][j] = self.data[i][j] + other.data[i][j];
-                }
+            let mut particular = vec![Rational::zero(); n];
+            for (row, &col) in pivots.iter().enumerate() {
+                particular[col] = reduced[(row, n)];
+            }
+            if pivots.len() == n {
+                return Ok(Solution::Unique(particular));
             }
 
-            Ok(result)
+            let null_space = (0..n)
+                .filter(|col| !pivots.contains(col))
+                .map(|free| {
+                    let mut direction = vec![Rational::zero(); n];
+                    direction[free] = Rational::one();
+                    for (row, &col) in pivots.iter().enumerate() {
+                        direction[col] = reduced[(row, free)]
+                            .checked_negate()
+                            .ok_or(MatrixError::Overflow { op: "solve", row, col: free })?;
+                    }
+                    Ok(direction)
+                })
+                .collect::<Result<_, MatrixError>>()?;
+            Ok(Solution::Infinite { particular, null_space })
         }
+    }
 
-        pub fn subtract(&self, other: &Matrix) -> Result<Matrix, &'static str> {
-            if self.data.len() != other.data.len() || self.data[0].len() != other.data[0].len() {
-                return Err("Matrices must have the same dimensions for subtraction.");
-            }
+    // Elementary row operations, with 0-based row indices
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum RowOp {
+        Swap(usize, usize),
+        // row ← factor · row
+        Scale { row: usize, factor: Rational },
+        // target ← target + factor · source
+        AddMultiple { target: usize, source: usize, factor: Rational },
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct RowStep {
+        pub op: RowOp,
+        // The matrix right after `op` was applied
+        pub matrix: Matrix<Rational>,
+    }
 
-            let mut result = Matrix::new(self.data.len(), self.data[0].len());
+    // Log of a reduction to reduced row echelon form, renderable for teaching material
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct RowReduction {
+        pub start: Matrix<Rational>,
+        pub steps: Vec<RowStep>,
+        pub result: Matrix<Rational>,
+        pub pivots: Vec<usize>,
+    }
 
-            for i in 0..self.data.len() {
-                for j in 0..self.data[0].len() {
-                    result.data[i][j] = self.data[i][j] - other.data[i][j];
+    impl RowOp {
+        // Rows are written R1, R2, ... as in textbooks
+        pub fn to_text(&self) -> String {
+            match self {
+                RowOp::Swap(a, b) => format!("R{} <-> R{}", a + 1, b + 1),
+                RowOp::Scale { row, factor } => format!("R{} -> {}R{}", row + 1, text_coefficient(factor), row + 1),
+                RowOp::AddMultiple { target, source, factor } => {
+                    let (sign, magnitude) = signed(factor);
+                    let coefficient = if magnitude == Rational::one() { String::new() } else { text_coefficient(&magnitude) };
+                    format!("R{} -> R{} {} {}R{}", target + 1, target + 1, sign, coefficient, source + 1)
                 }
             }
+        }
 
-            Ok(result)
+        pub fn to_latex(&self) -> String {
+            match self {
+                RowOp::Swap(a, b) => format!("R_{{{}}} \\leftrightarrow R_{{{}}}", a + 1, b + 1),
+                RowOp::Scale { row, factor } => {
+                    format!("R_{{{}}} \\to {} R_{{{}}}", row + 1, latex_rational(factor), row + 1)
+                }
+                RowOp::AddMultiple { target, source, factor } => {
+                    let (sign, magnitude) = signed(factor);
+                    let coefficient = if magnitude == Rational::one() { String::new() } else { latex_rational(&magnitude) };
+                    format!("R_{{{}}} \\to R_{{{}}} {} {} R_{{{}}}", target + 1, target + 1, sign, coefficient, source + 1)
+                }
+            }
         }
+    }
 
-        pub fn multiply(&self, other: &Matrix) -> Result<Matrix, &'static str> {
-            if self.data[0].len() != other.data.len() {
-                return Err("Invalid dimensions for matrix multiplication.");
+    impl RowReduction {
+        pub fn to_text(&self) -> String {
+            let mut text = format!("Start:\n{}", text_matrix(&self.start));
+            for (index, step) in self.steps.iter().enumerate() {
+                text += &format!("\nStep {}: {}\n{}", index + 1, step.op.to_text(), text_matrix(&step.matrix));
             }
+            text += &format!("\nPivot columns: {}\n", pivot_list(&self.pivots));
+            text
+        }
 
-            let mut result = Matrix::new(self.data.len(), other.data[0].len());
+        pub fn to_markdown(&self) -> String {
+            let mut text = format!("**Start**\n\n{}", markdown_matrix(&self.start));
+            for (index, step) in self.steps.iter().enumerate() {
+                text += &format!("\n**Step {}:** `{}`\n\n{}", index + 1, step.op.to_text(), markdown_matrix(&step.matrix));
+            }
+            text += &format!("\nPivot columns: {}\n", pivot_list(&self.pivots));
+            text
+        }
 
-            for i in 0..self.data.len() {
-                for j in 0..other.data[0].len() {
-                    let mut multiply = 0;
-                    for k in 0..other.data[0].len() {
-                        multiply += self.data[i][k] * other.data[k][j];
-                    }
-                    result.data[i][j] = multiply;
-                }
+        // An align* environment chaining each matrix to the next with the operation over the arrow
+        pub fn to_latex(&self) -> String {
+            let mut text = format!("\\begin{{align*}}\n  & {}", latex_matrix(&self.start));
+            for step in &self.steps {
+                text += &format!(" \\\\\n  \\xrightarrow{{{}}} & {}", step.op.to_latex(), latex_matrix(&step.matrix));
             }
-            Ok(result)
+            text += "\n\\end{align*}\n";
+            text
+        }
+    }
+
+    // A factor of i64::MIN has no positive counterpart and keeps its own sign
+    fn signed(factor: &Rational) -> (&'static str, Rational) {
+        match factor.checked_negate() {
+            Some(magnitude) if *factor < Rational::zero() => ("-", magnitude),
+            _ => ("+", *factor),
         }
+    }
 
-        pub fn scalar_multiply(&self, scalar: i32) -> Matrix {
-            let mut result = Matrix::new(self.data.len(), self.data[0].len());
+    // Fractions are parenthesized so that (1/2)R1 does not read as 1/(2R1)
+    fn text_coefficient(factor: &Rational) -> String {
+        if factor.is_integer() { factor.to_string() } else { format!("({})", factor) }
+    }
 
-            for i in 0..self.data.len() {
-                for j in 0..self.data[0].len() {
-                    result.data[i][j] = self.data[i][j] * scalar;
-                }
-            }
+    fn pivot_list(pivots: &[usize]) -> String {
+        let columns: Vec<String> = pivots.iter().map(|col| (col + 1).to_string()).collect();
+        if columns.is_empty() { "none".to_string() } else { columns.join(", ") }
+    }
 
-            result
+    // Rows in brackets with right-aligned columns
+    fn text_matrix(matrix: &Matrix<Rational>) -> String {
+        let cells: Vec<String> = matrix.data.iter().map(|x| x.to_string()).collect();
+        let widths: Vec<usize> = (0..matrix.cols)
+            .map(|j| (0..matrix.rows).map(|i| cells[i * matrix.cols + j].chars().count()).max().unwrap_or(0))
+            .collect();
+        let mut text = String::new();
+        for i in 0..matrix.rows {
+            let row: Vec<String> = (0..matrix.cols)
+                .map(|j| format!("{:>width$}", cells[i * matrix.cols + j], width = widths[j]))
+                .collect();
+            text += &format!("[ {} ]\n", row.join("  "));
         }
+        text
+    }
+
+    fn markdown_matrix(matrix: &Matrix<Rational>) -> String {
+        let header: Vec<String> = (1..=matrix.cols).map(|j| format!("C{}", j)).collect();
+        let mut table = format!("| {} |\n|{}\n", header.join(" | "), "---|".repeat(matrix.cols));
+        for i in 0..matrix.rows {
+            let row: Vec<String> = matrix.row(i).iter().map(|x| x.to_string()).collect();
+            table += &format!("| {} |\n", row.join(" | "));
+        }
+        table
+    }
+
+    fn latex_rational(value: &Rational) -> String {
+        if value.is_integer() {
+            value.to_string()
+        } else {
+            let sign = if value.numerator() < 0 { "-" } else { "" };
+            format!("{}\\frac{{{}}}{{{}}}", sign, value.numerator().unsigned_abs(), value.denominator())
+        }
+    }
+
+    fn latex_matrix(matrix: &Matrix<Rational>) -> String {
+        let rows: Vec<String> = (0..matrix.rows)
+            .map(|i| matrix.row(i).iter().map(latex_rational).collect::<Vec<_>>().join(" & "))
+            .collect();
+        format!("\\begin{{bmatrix}} {} \\end{{bmatrix}}", rows.join(" \\\\ "))
     }
 
-    pub fn read_matrices_from_csv(filename: &str) -> Result<Vec<Matrix>, Box<dyn Error>> {
+    pub fn read_matrices_from_csv<T>(filename: &str) -> Result<Vec<Matrix<T>>, Box<dyn Error>>
+    where
+        T: Numeric + FromStr,
+    {
         let path = Path::new(filename);
-        let file = File::open(&path)?;
+        let file = File::open(path)?;
         let reader = BufReader::new(file);
 
         let mut matrices = Vec::new();
-        let mut current_matrix = Matrix::new(0, 0);
+        let mut current_rows: Vec<Vec<T>> = Vec::new();
 
-        for line in reader.lines() {
+        for (index, line) in reader.lines().enumerate() {
             let line = line?;
+            let line_number = index + 1;
             if line.trim().is_empty() {
-                matrices.push(current_matrix);
-                current_matrix = Matrix::new(0, 0);
+                matrices.push(Matrix::from_rows(current_rows)?);
+                current_rows = Vec::new();
             } else {
-                let row: Vec<i32> = line
+                let row = line
                     .split(',')
-                    .map(|s| s.trim().parse::<i32>().unwrap())
-                    .collect();
-                current_matrix.data.push(row);
+                    .enumerate()
+                    .map(|(col, s)| s.trim().parse::<T>().map_err(|_| MatrixError::Parse { line: line_number, col: col + 1 }))
+                    .collect::<Result<Vec<T>, _>>()?;
+                if let Some(first) = current_rows.first() {
+                    if first.len() != row.len() {
+                        return Err(Box::new(MatrixError::RaggedRow { line: line_number, expected: first.len(), found: row.len() }));
+                    }
+                }
+                current_rows.push(row);
             }
         }
 
-        matrices.push(current_matrix); // Add the last matrix
+        matrices.push(Matrix::from_rows(current_rows)?); // Add the last matrix
         Ok(matrices)
     }
 
-    pub fn print_matrix(matrix: &Matrix) {
-        for row in &matrix.data {
+    pub fn print_matrix<T: Debug>(matrix: &Matrix<T>) {
+        for row in matrix.data.chunks(matrix.cols.max(1)) {
             println!("{:?}", row);
         }
     }
 
     #[test]
     fn test_matrix_operations() {
-        let matrix1 = Matrix { data: vec![vec![1, 2], vec![3, 4]] };
-        let matrix2 = Matrix { data: vec![vec![5, 6], vec![7, 8]] };
+        let matrix1 = Matrix::from_rows(vec![vec![1, 2], vec![3, 4]]).unwrap();
+        let matrix2 = Matrix::from_rows(vec![vec![5, 6], vec![7, 8]]).unwrap();
         let scalar = 2;
 
         // Test addition
         let addition_result = matrix1.add(&matrix2);
-        let expected_addition = Matrix { data: vec![vec![6, 8], vec![10, 12]] };
+        let expected_addition = Matrix::from_rows(vec![vec![6, 8], vec![10, 12]]).unwrap();
         assert_eq!(addition_result, Ok(expected_addition));
 
         // Test subtraction
         let subtraction_result = matrix1.subtract(&matrix2);
-        let expected_subtraction = Matrix { data: vec![vec![-4, -4], vec![-4, -4]] };
+        let expected_subtraction = Matrix::from_rows(vec![vec![-4, -4], vec![-4, -4]]).unwrap();
         assert_eq!(subtraction_result, Ok(expected_subtraction));
 
         // Test multiplication
         let multiplication_result = matrix1.multiply(&matrix2);
-        let expected_multiplication = Matrix { data: vec![vec![19, 22], vec![43, 50]] };
+        let expected_multiplication = Matrix::from_rows(vec![vec![19, 22], vec![43, 50]]).unwrap();
         assert_eq!(multiplication_result, Ok(expected_multiplication));
 
         // Test scalar multiplication
         let scalar_result = matrix1.scalar_multiply(scalar);
-        let expected_scalar_result = Matrix { data: vec![vec![2, 4], vec![6, 8]] };
+        let expected_scalar_result = Matrix::from_rows(vec![vec![2, 4], vec![6, 8]]).unwrap();
         assert_eq!(scalar_result, expected_scalar_result);
+
+        // Test floating-point and complex elements
+        let float_matrix = Matrix::from_rows(vec![vec![0.5, 1.0], vec![1.5, 2.0]]).unwrap();
+        let float_result = float_matrix.multiply(&float_matrix);
+        assert_eq!(float_result, Ok(Matrix::from_rows(vec![vec![1.75, 2.5], vec![3.75, 5.5]]).unwrap()));
+
+        let i = Complex::new(0.0, 1.0);
+        let complex_matrix = Matrix::from_rows(vec![vec![Complex::new(1.0, 0.0), i.clone()], vec![i.clone(), Complex::new(1.0, 0.0)]]).unwrap();
+        let complex_result = complex_matrix.multiply(&complex_matrix);
+        assert_eq!(complex_result, Ok(Matrix::from_rows(vec![vec![Complex::new(0.0, 0.0), Complex::new(0.0, 2.0)], vec![Complex::new(0.0, 2.0), Complex::new(0.0, 0.0)]]).unwrap()));
+        assert_eq!(complex_matrix.scalar_multiply(i.clone())[(0, 0)], i);
+    }
+
+    #[test]
+    fn test_matrix_layout() {
+        let wide = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        let tall = Matrix::from_rows(vec![vec![1], vec![0], vec![-1]]).unwrap();
+
+        // Test non-square products use the inner dimension
+        assert_eq!(wide.multiply(&tall), Ok(Matrix::from_rows(vec![vec![-2], vec![-2]]).unwrap()));
+        let row = Matrix::from_rows(vec![vec![2, 5]]).unwrap();
+        assert_eq!(tall.multiply(&row).map(|m| (m.rows(), m.cols())), Ok((3, 2)));
+        assert!(wide.multiply(&wide).is_err());
+
+        // Test the tiled kernel across block boundaries against a direct sum
+        let size = 70;
+        let a = Matrix::from_vec(size, size + 3, (0..size * (size + 3)).map(|x| (x % 7) as i64 - 3).collect()).unwrap();
+        let b = Matrix::from_vec(size + 3, size - 1, (0..(size + 3) * (size - 1)).map(|x| (x % 5) as i64 - 2).collect()).unwrap();
+        let product = a.multiply(&b).unwrap();
+        for (i, j) in [(0, 0), (63, 64), (69, 68), (12, 40)] {
+            let expected: i64 = (0..size + 3).map(|k| a[(i, k)] * b[(k, j)]).sum();
+            assert_eq!(product[(i, j)], expected);
+        }
+
+        // Test layout accessors
+        assert_eq!(wide.row(1), &[4, 5, 6]);
+        assert_eq!(wide.get(1, 2), Some(&6));
+        assert_eq!(wide.get(2, 0), None);
+        assert_eq!(wide.to_rows(), vec![vec![1, 2, 3], vec![4, 5, 6]]);
+    }
+
+    #[test]
+    fn test_integer_overflow_modes() {
+        let matrix1 = Matrix::from_rows(vec![vec![1, 2], vec![3, 4]]).unwrap();
+        let matrix2 = Matrix::from_rows(vec![vec![5, 6], vec![7, 8]]).unwrap();
+        let wide = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        let tall = Matrix::from_rows(vec![vec![1], vec![0], vec![-1]]).unwrap();
+
+        // Test overflow-aware variants
+        let big = Matrix::from_rows(vec![vec![i32::MAX, 1], vec![-5, i32::MIN]]).unwrap();
+        let ones = Matrix::from_rows(vec![vec![1, 1], vec![1, 1]]).unwrap();
+        assert_eq!(big.checked_add(&ones), Err(MatrixError::Overflow { op: "addition", row: 0, col: 0 }));
+        assert_eq!(big.checked_subtract(&ones), Err(MatrixError::Overflow { op: "subtraction", row: 1, col: 1 }));
+        assert_eq!(big.checked_multiply(&ones), Err(MatrixError::Overflow { op: "multiplication", row: 0, col: 0 }));
+        assert_eq!(big.checked_scalar_multiply(2), Err(MatrixError::Overflow { op: "scalar multiplication", row: 0, col: 0 }));
+        assert_eq!(matrix1.checked_multiply(&matrix2).unwrap(), matrix1.multiply(&matrix2).unwrap());
+        assert_eq!(
+            wide.checked_add(&tall),
+            Err(MatrixError::DimensionMismatch { op: "addition", left: (2, 3), right: (3, 1) })
+        );
+        assert_eq!(
+            MatrixError::Overflow { op: "addition", row: 0, col: 1 }.to_string(),
+            "addition overflowed at row 0, column 1"
+        );
+
+        assert_eq!(big.saturating_add(&ones).unwrap().to_rows(), vec![vec![i32::MAX, 2], vec![-4, i32::MIN + 1]]);
+        assert_eq!(big.saturating_scalar_multiply(2).to_rows(), vec![vec![i32::MAX, 2], vec![-10, i32::MIN]]);
+        assert_eq!(big.saturating_multiply(&ones).unwrap()[(1, 0)], i32::MIN);
+        // The result is the exact sum clamped once, whatever the order of the terms
+        let mixed = Matrix::from_rows(vec![vec![i32::MAX, 5, -7]]).unwrap();
+        let reordered = Matrix::from_rows(vec![vec![-7, i32::MAX, 5]]).unwrap();
+        let all_ones = Matrix::from_rows(vec![vec![1], vec![1], vec![1]]).unwrap();
+        assert_eq!(mixed.saturating_multiply(&all_ones).unwrap()[(0, 0)], i32::MAX - 2);
+        assert_eq!(reordered.saturating_multiply(&all_ones).unwrap()[(0, 0)], i32::MAX - 2);
+        let extremes = Matrix::from_rows(vec![vec![i32::MIN, i32::MIN, i32::MIN, i32::MAX]]).unwrap();
+        let column = Matrix::from_rows(vec![vec![i32::MIN], vec![i32::MIN], vec![i32::MIN], vec![i32::MIN]]).unwrap();
+        assert_eq!(extremes.saturating_multiply(&column).unwrap()[(0, 0)], i32::MAX);
+        assert_eq!(column.transpose().saturating_multiply(&column.map(|&x| -(x + 1))).unwrap()[(0, 0)], i32::MIN);
+        let huge = Matrix::from_rows(vec![vec![i128::MAX, i128::MAX, -i128::MAX, 3]]).unwrap();
+        let huge_column = Matrix::from_rows(vec![vec![2], vec![-1], vec![1], vec![-1]]).unwrap();
+        assert_eq!(huge.saturating_multiply(&huge_column).unwrap()[(0, 0)], -3);
+        let pushed = Matrix::from_rows(vec![vec![i128::MAX, 2, -i128::MAX]]).unwrap();
+        let pushed_column = Matrix::from_rows(vec![vec![2i128], vec![1], vec![1]]).unwrap();
+        assert_eq!(pushed.saturating_multiply(&pushed_column).unwrap()[(0, 0)], i128::MAX);
+        let squares = Matrix::from_rows(vec![vec![i128::MIN, i128::MIN]]).unwrap();
+        assert_eq!(squares.saturating_multiply(&squares.transpose()).unwrap()[(0, 0)], i128::MAX);
+        assert_eq!(squares.saturating_multiply(&squares.transpose().map(|&x| -(x + 1))).unwrap()[(0, 0)], i128::MIN);
+        let exact = Matrix::from_rows(vec![vec![i128::MIN, 1]]).unwrap();
+        let exact_column = Matrix::from_rows(vec![vec![1i128], vec![-1]]).unwrap();
+        assert_eq!(exact.saturating_multiply(&exact_column).unwrap()[(0, 0)], i128::MIN);
+        let undone = Matrix::from_rows(vec![vec![i128::MIN, 5, -7]]).unwrap();
+        let undone_column = Matrix::from_rows(vec![vec![-1i128], vec![1], vec![1]]).unwrap();
+        assert_eq!(undone.saturating_multiply(&undone_column).unwrap()[(0, 0)], i128::MAX - 1);
+        let small = Matrix::from_rows(vec![vec![100i8, -100, 100]]).unwrap();
+        let small_ones = Matrix::from_rows(vec![vec![2i8], vec![2], vec![-1]]).unwrap();
+        assert_eq!(small.saturating_multiply(&small_ones).unwrap()[(0, 0)], -100);
+        assert_eq!(big.wrapping_add(&ones).unwrap()[(0, 0)], i32::MIN);
+        assert_eq!(big.wrapping_multiply(&ones).unwrap()[(0, 0)], i32::MIN);
+        assert_eq!(big.wrapping_scalar_multiply(2)[(1, 1)], 0);
+
+        // Test widening accumulation
+        let widened = big.widening_multiply(&ones).unwrap();
+        assert_eq!(widened[(0, 0)], i32::MAX as i64 + 1);
+        assert_eq!(widened[(1, 0)], i32::MIN as i64 - 5);
+        assert_eq!(big.widening_scalar_multiply(2).unwrap()[(0, 0)], 2 * i32::MAX as i64);
+        let huge = Matrix::from_rows(vec![vec![i64::MAX, i64::MAX]]).unwrap();
+        let column = Matrix::from_rows(vec![vec![i64::MAX], vec![i64::MAX]]).unwrap();
+        assert_eq!(huge.widening_multiply(&column).unwrap()[(0, 0)], 2 * (i64::MAX as i128).pow(2));
+    }
+
+    #[test]
+    fn test_matrix_errors() {
+        let matrix1 = Matrix::from_rows(vec![vec![1, 2], vec![3, 4]]).unwrap();
+        let wide = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+        let tall = Matrix::from_rows(vec![vec![1], vec![0], vec![-1]]).unwrap();
+
+        // Test structured errors
+        assert_eq!(
+            wide.add(&tall),
+            Err(MatrixError::DimensionMismatch { op: "addition", left: (2, 3), right: (3, 1) })
+        );
+        assert_eq!(
+            wide.multiply(&wide).unwrap_err().to_string(),
+            "cannot apply multiplication to a 2×3 and a 2×3 matrix"
+        );
+        let empty: Matrix<i32> = Matrix::new(0, 0);
+        assert_eq!(empty.add(&empty), Err(MatrixError::Empty));
+        assert_eq!(empty.multiply(&matrix1), Err(MatrixError::Empty));
+        assert_eq!(matrix1.subtract(&Matrix::new(2, 0)), Err(MatrixError::Empty));
+        assert_eq!(empty.scalar_multiply(3), empty);
+        assert_eq!(
+            Matrix::from_rows(vec![vec![1, 2], vec![3]]),
+            Err(MatrixError::RaggedRow { line: 2, expected: 2, found: 1 })
+        );
+        assert_eq!(Matrix::from_vec(2, 2, vec![1]), Err(MatrixError::DataLength { expected: 4, found: 1 }));
+        let boxed: Box<dyn Error> = Box::new(MatrixError::Parse { line: 3, col: 2 });
+        assert_eq!(boxed.to_string(), "invalid number at line 3, column 2");
+    }
+
+    #[test]
+    fn test_matrix_properties() {
+        let matrix1 = Matrix::from_rows(vec![vec![1, 2], vec![3, 4]]).unwrap();
+        let wide = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+
+        // Test transpose, trace and constructors
+        assert_eq!(wide.transpose(), Matrix::from_rows(vec![vec![1, 4], vec![2, 5], vec![3, 6]]).unwrap());
+        assert_eq!(wide.transpose().transpose(), wide);
+        assert_eq!(matrix1.trace(), Ok(5));
+        assert_eq!(wide.trace(), Err(MatrixError::NotSquare { rows: 2, cols: 3 }));
+        assert_eq!(Matrix::<i32>::identity(2).multiply(&matrix1), Ok(matrix1.clone()));
+        assert_eq!(Matrix::<i32>::zeros(2, 3).as_slice(), &[0; 6]);
+
+        // Test exact integer determinant and rank
+        assert_eq!(matrix1.determinant(), Ok(-2));
+        assert_eq!(Matrix::from_rows(vec![vec![0, 1], vec![1, 0]]).unwrap().determinant(), Ok(-1));
+        let singular = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]).unwrap();
+        assert_eq!(singular.determinant(), Ok(0));
+        assert_eq!(singular.rank(), Ok(2));
+        let matrix3 = Matrix::from_rows(vec![vec![2, -3, 1], vec![2, 0, -1], vec![1, 4, 5]]).unwrap();
+        assert_eq!(matrix3.determinant(), Ok(49));
+        assert_eq!(matrix3.rank(), Ok(3));
+        assert_eq!(wide.rank(), Ok(2));
+        assert_eq!(Matrix::from_rows(vec![vec![0, 0], vec![0, 0]]).unwrap().rank(), Ok(0));
+        assert_eq!(wide.determinant(), Err(MatrixError::NotSquare { rows: 2, cols: 3 }));
+        assert_eq!(Matrix::<i64>::new(0, 0).determinant(), Err(MatrixError::Empty));
+        let big = Matrix::from_rows(vec![vec![i32::MAX, 1], vec![1, i32::MAX]]).unwrap();
+        assert!(matches!(big.determinant(), Err(MatrixError::Overflow { op: "determinant", .. })));
+        assert_eq!(big.widen().determinant(), Ok(i32::MAX as i64 * i32::MAX as i64 - 1));
+
+        // Test floating-point determinant and rank
+        let float = Matrix::from_rows(vec![vec![0.0, 2.0, 1.0], vec![1.0, 1.0, 0.0], vec![3.0, 0.0, 1.0]]).unwrap();
+        assert!((float.determinant().unwrap() - -5.0f64).abs() < 1e-12);
+        assert_eq!(float.rank(), Ok(3));
+        let float_singular = Matrix::from_rows(vec![vec![1.0, 2.0], vec![0.5, 1.0]]).unwrap();
+        assert_eq!(float_singular.determinant(), Ok(0.0f32));
+        assert_eq!(float_singular.rank(), Ok(1));
+    }
+
+    #[test]
+    fn test_rational_matrices() {
+        let r = |n: i64, d: i64| Rational::new(n, d);
+        let singular = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]).unwrap();
+        let wide = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+
+        // Test exact rational inverse, rref and solve
+        let exact = Matrix::from_rows(vec![vec![2, 1], vec![5, 3]]).unwrap().map(|&x| Rational::from(x));
+        let inverse = exact.inverse().unwrap();
+        assert_eq!(inverse, Matrix::from_rows(vec![vec![r(3, 1), r(-1, 1)], vec![r(-5, 1), r(2, 1)]]).unwrap());
+        assert_eq!(exact.multiply(&inverse), Ok(Matrix::identity(2)));
+        let thirds = Matrix::from_rows(vec![vec![r(3, 1), r(0, 1)], vec![r(0, 1), r(3, 1)]]).unwrap();
+        assert_eq!(thirds.inverse().unwrap()[(0, 0)], r(1, 3));
+        assert_eq!(thirds.determinant(), Ok(r(9, 1)));
+        let rational_singular = singular.map(|&x| Rational::from(x));
+        assert_eq!(rational_singular.inverse(), Err(MatrixError::Singular));
+        assert_eq!(rational_singular.determinant(), Ok(r(0, 1)));
+        assert_eq!(rational_singular.rank(), Ok(2));
+        assert_eq!(wide.map(|&x| Rational::from(x)).inverse(), Err(MatrixError::NotSquare { rows: 2, cols: 3 }));
+        assert_eq!(
+            rational_singular.rref(),
+            Matrix::from_rows(vec![
+                vec![r(1, 1), r(0, 1), r(-1, 1)],
+                vec![r(0, 1), r(1, 1), r(2, 1)],
+                vec![r(0, 1), r(0, 1), r(0, 1)],
+            ])
+        );
+        assert_eq!(exact.solve(&[r(1, 1), r(2, 1)]), Ok(Solution::Unique(vec![r(1, 1), r(-1, 1)])));
+        assert_eq!(
+            rational_singular.solve(&[r(6, 1), r(15, 1), r(24, 1)]),
+            Ok(Solution::Infinite {
+                particular: vec![r(0, 1), r(3, 1), r(0, 1)],
+                null_space: vec![vec![r(1, 1), r(-2, 1), r(1, 1)]],
+            })
+        );
+        assert_eq!(rational_singular.solve(&[r(1, 1), r(0, 1), r(0, 1)]), Err(MatrixError::Inconsistent));
+        assert!(matches!(exact.solve(&[r(1, 1)]), Err(MatrixError::DimensionMismatch { op: "solve", .. })));
+
+        // Test that entries leaving the i64 range are reported instead of panicking
+        let huge = Matrix::from_rows(vec![vec![r(i64::MAX, 1), r(1, 1)], vec![r(1, 1), r(i64::MAX, 1)]]).unwrap();
+        assert_eq!(huge.rref(), Err(MatrixError::Overflow { op: "rref", row: 1, col: 1 }));
+        assert_eq!(huge.inverse(), Err(MatrixError::Overflow { op: "inverse", row: 1, col: 1 }));
+        assert_eq!(huge.solve(&[r(1, 1), r(1, 1)]), Err(MatrixError::Overflow { op: "solve", row: 1, col: 1 }));
+        assert_eq!(huge.determinant(), Err(MatrixError::Overflow { op: "determinant", row: 1, col: 1 }));
+        let minimum = Matrix::from_rows(vec![vec![r(1, 1), r(i64::MIN, 1)]]).unwrap();
+        assert_eq!(minimum.solve(&[r(0, 1)]), Err(MatrixError::Overflow { op: "solve", row: 0, col: 1 }));
+        assert_eq!(huge.rank(), Err(MatrixError::Overflow { op: "rank", row: 1, col: 1 }));
+        assert!(huge.explain_rref().is_err());
+    }
+
+    #[test]
+    fn test_row_reduction_explainer() {
+        let r = |n: i64, d: i64| Rational::new(n, d);
+        let exact = Matrix::from_rows(vec![vec![2, 1], vec![5, 3]]).unwrap().map(|&x| Rational::from(x));
+        let rational_singular = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]).unwrap().map(|&x| Rational::from(x));
+
+        // Test the row reduction explainer
+        let explained = exact.explain_rref().unwrap();
+        assert_eq!(Ok(explained.result.clone()), exact.rref());
+        assert_eq!(explained.result, Matrix::identity(2));
+        assert_eq!(
+            explained.steps.iter().map(|step| step.op.clone()).collect::<Vec<_>>(),
+            vec![
+                RowOp::Scale { row: 0, factor: r(1, 2) },
+                RowOp::AddMultiple { target: 1, source: 0, factor: r(-5, 1) },
+                RowOp::Scale { row: 1, factor: r(2, 1) },
+                RowOp::AddMultiple { target: 0, source: 1, factor: r(-1, 2) },
+            ]
+        );
+        assert_eq!(explained.steps[0].matrix.row(0), &[r(1, 1), r(1, 2)]);
+        let swapped = Matrix::from_rows(vec![vec![r(0, 1), r(1, 1)], vec![r(1, 1), r(0, 1)]]).unwrap().explain_rref().unwrap();
+        assert_eq!(swapped.steps.len(), 1);
+        assert_eq!(swapped.steps[0].op.to_text(), "R1 <-> R2");
+        assert_eq!(swapped.steps[0].op.to_latex(), "R_{1} \\leftrightarrow R_{2}");
+        assert_eq!(explained.steps[1].op.to_text(), "R2 -> R2 - 5R1");
+        assert_eq!(explained.steps[3].op.to_text(), "R1 -> R1 - (1/2)R2");
+        assert_eq!(explained.steps[3].op.to_latex(), "R_{1} \\to R_{1} - \\frac{1}{2} R_{2}");
+        let text = explained.to_text();
+        assert!(text.starts_with("Start:\n[ 2  1 ]\n[ 5  3 ]\n"));
+        assert!(text.contains("Step 1: R1 -> (1/2)R1\n[ 1  1/2 ]\n[ 5    3 ]\n"));
+        assert!(text.ends_with("Pivot columns: 1, 2\n"));
+        let markdown = explained.to_markdown();
+        assert!(markdown.contains("**Step 2:** `R2 -> R2 - 5R1`\n\n| C1 | C2 |\n|---|---|\n| 1 | 1/2 |\n| 0 | 1/2 |\n"));
+        let latex = explained.to_latex();
+        assert!(latex.starts_with("\\begin{align*}\n  & \\begin{bmatrix} 2 & 1 \\\\ 5 & 3 \\end{bmatrix}"));
+        assert!(latex.contains("\\xrightarrow{R_{1} \\to \\frac{1}{2} R_{1}} & \\begin{bmatrix} 1 & \\frac{1}{2}"));
+        assert!(latex.ends_with("\\end{align*}\n"));
+        assert!(rational_singular.explain_rref().unwrap().to_text().ends_with("Pivot columns: 1, 2\n"));
+
+        // Test narration of factors at the edge of the i64 range
+        let minimum = RowOp::AddMultiple { target: 0, source: 1, factor: r(i64::MIN, 1) };
+        assert_eq!(minimum.to_text(), "R1 -> R1 + -9223372036854775808R2");
+        assert_eq!(RowOp::Scale { row: 0, factor: r(i64::MIN, 3) }.to_latex(), "R_{1} \\to -\\frac{9223372036854775808}{3} R_{1}");
+    }
+
+    #[test]
+    fn test_matrix_decompositions() {
+        // Test LU, QR and Cholesky decompositions
+        let a = Matrix::from_rows(vec![vec![2.0, 1.0, 1.0], vec![4.0, -6.0, 0.0], vec![-2.0, 7.0, 2.0]]).unwrap();
+        let lu = a.lu().unwrap();
+        assert_eq!(lu.permutation, vec![1, 0, 2]);
+        assert!(lu.permutation_matrix().multiply(&a).unwrap().approx_eq(&lu.lower.multiply(&lu.upper).unwrap(), 1e-12));
+        assert!((0..3).all(|i| lu.lower[(i, i)] == 1.0 && (i + 1..3).all(|j| lu.lower[(i, j)] == 0.0 && lu.upper[(j, i)] == 0.0)));
+        let reordered = Matrix::from_rows(vec![vec![1.0, 9.0, 1.0], vec![2.0, 1.0, 5.0], vec![4.0, 6.0, 8.0]]).unwrap();
+        let reordered_lu = reordered.lu().unwrap();
+        assert_eq!(reordered_lu.permutation, vec![2, 0, 1]);
+        assert!(reordered_lu.permutation_matrix().multiply(&reordered).unwrap().approx_eq(&reordered_lu.lower.multiply(&reordered_lu.upper).unwrap(), 1e-12));
+        let singular_lu = Matrix::from_rows(vec![vec![1.0, 2.0], vec![2.0, 4.0]]).unwrap().lu().unwrap();
+        assert_eq!(singular_lu.upper[(1, 1)], 0.0);
+        assert_eq!(Matrix::<f64>::new(2, 3).lu(), Err(MatrixError::NotSquare { rows: 2, cols: 3 }));
+
+        let samples = Matrix::from_rows(vec![vec![1.0, -1.0], vec![1.0, 4.0], vec![1.0, 2.0], vec![1.0, 0.0f64]]).unwrap();
+        let qr = samples.qr().unwrap();
+        assert_eq!((qr.q.rows(), qr.r.rows(), qr.r.cols()), (4, 4, 2));
+        assert!(qr.q.multiply(&qr.r).unwrap().approx_eq(&samples, 1e-12));
+        assert!(qr.q.transpose().multiply(&qr.q).unwrap().approx_eq(&Matrix::identity(4), 1e-12));
+        assert!((1..4).all(|i| (0..i.min(2)).all(|j| qr.r[(i, j)] == 0.0)));
+        let square_qr = a.qr().unwrap();
+        assert!(square_qr.q.multiply(&square_qr.r).unwrap().approx_eq(&a, 1e-12));
+        assert_eq!(Matrix::<f32>::new(0, 2).qr(), Err(MatrixError::Empty));
+
+        let spd = Matrix::from_rows(vec![vec![4.0, 12.0, -16.0], vec![12.0, 37.0, -43.0], vec![-16.0, -43.0, 98.0]]).unwrap();
+        let l = spd.cholesky().unwrap();
+        assert!(l.approx_eq(&Matrix::from_rows(vec![vec![2.0, 0.0, 0.0], vec![6.0, 1.0, 0.0], vec![-8.0, 5.0, 3.0]]).unwrap(), 1e-12));
+        assert!(l.multiply(&l.transpose()).unwrap().approx_eq(&spd, 1e-12));
+        assert_eq!(a.cholesky(), Err(MatrixError::NotSymmetric));
+        assert_eq!(Matrix::from_rows(vec![vec![1.0, 2.0], vec![2.0, 1.0]]).unwrap().cholesky(), Err(MatrixError::NotPositiveDefinite));
+    }
+
+    #[test]
+    fn test_eigen_and_svd() {
+        let a = Matrix::from_rows(vec![vec![2.0, 1.0, 1.0], vec![4.0, -6.0, 0.0], vec![-2.0, 7.0, 2.0]]).unwrap();
+        let wide = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+
+        // Test symmetric eigen-decomposition
+        let symmetric = Matrix::from_rows(vec![vec![2.0, -1.0, 0.0], vec![-1.0, 2.0, -1.0], vec![0.0, -1.0, 2.0]]).unwrap();
+        let eigen = symmetric.symmetric_eigen().unwrap();
+        let expected = [2.0 + 2f64.sqrt(), 2.0, 2.0 - 2f64.sqrt()];
+        assert!(eigen.values.iter().zip(&expected).all(|(value, expected)| (value - expected).abs() < 1e-12));
+        let mut diagonal = Matrix::new(3, 3);
+        for (i, value) in eigen.values.iter().enumerate() {
+            diagonal[(i, i)] = *value;
+        }
+        let rebuilt = eigen.vectors.multiply(&diagonal).unwrap().multiply(&eigen.vectors.transpose()).unwrap();
+        assert!(rebuilt.approx_eq(&symmetric, 1e-12));
+        assert!(eigen.vectors.transpose().multiply(&eigen.vectors).unwrap().approx_eq(&Matrix::identity(3), 1e-12));
+        assert_eq!(a.symmetric_eigen(), Err(MatrixError::NotSymmetric));
+
+        // Test general eigenvalues, real and complex
+        let close = |z: &(f64, f64), real: f64, imaginary: f64| (z.0 - real).abs() < 1e-9 && (z.1 - imaginary).abs() < 1e-9;
+        let rotation = Matrix::from_rows(vec![vec![0.0, -1.0], vec![1.0, 0.0]]).unwrap();
+        let values = rotation.eigenvalues().unwrap();
+        assert!(close(&values[0], 0.0, 1.0) && close(&values[1], 0.0, -1.0));
+        let cycle = Matrix::from_rows(vec![vec![0.0, 0.0, 1.0], vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]]).unwrap();
+        let values = cycle.eigenvalues().unwrap();
+        let half_root3 = 3f64.sqrt() / 2.0;
+        assert!(close(&values[0], 1.0, 0.0) && close(&values[1], -0.5, half_root3) && close(&values[2], -0.5, -half_root3));
+        let values = a.eigenvalues().unwrap();
+        let trace: f64 = values.iter().map(|z| z.0).sum();
+        assert!((trace - a.trace().unwrap() as f64).abs() < 1e-9);
+        let product = values.iter().fold((1.0, 0.0), |(re, im), z| (re * z.0 - im * z.1, re * z.1 + im * z.0));
+        assert!(close(&product, a.determinant().unwrap() as f64, 0.0));
+        let companion = Matrix::from_rows(vec![
+            vec![6.0, -11.0, 6.0, 0.0],
+            vec![1.0, 0.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0, 0.0],
+            vec![0.0, 0.0, 1.0, 0.0f32],
+        ])
+        .unwrap();
+        let values = companion.eigenvalues().unwrap();
+        assert!([3.0, 2.0, 1.0, 0.0].iter().zip(&values).all(|(&expected, z)| (z.0 - expected).abs() < 1e-4 && z.1 == 0.0));
+        // Digits past f32 precision survive
+        let precise = Matrix::from_rows(vec![vec![100_000_001.0, 1.0], vec![0.0, 2.0]]).unwrap();
+        assert!(close(&precise.eigenvalues().unwrap()[0], 100_000_001.0, 0.0));
+        assert_eq!(wide.map(|&x| x as f64).eigenvalues(), Err(MatrixError::NotSquare { rows: 2, cols: 3 }));
+
+        // Test singular value decomposition and condition number
+        let rectangular = Matrix::from_rows(vec![vec![3.0, 2.0, 2.0], vec![2.0, 3.0, -2.0]]).unwrap();
+        let svd = rectangular.svd().unwrap();
+        assert!((svd.singular_values[0] - 5.0).abs() < 1e-12 && (svd.singular_values[1] - 3.0).abs() < 1e-12);
+        assert_eq!((svd.u.rows(), svd.u.cols(), svd.v.rows(), svd.v.cols()), (2, 2, 3, 2));
+        let mut sigma = Matrix::new(2, 2);
+        sigma[(0, 0)] = svd.singular_values[0];
+        sigma[(1, 1)] = svd.singular_values[1];
+        let rebuilt = svd.u.multiply(&sigma).unwrap().multiply(&svd.v.transpose()).unwrap();
+        assert!(rebuilt.approx_eq(&rectangular, 1e-12));
+        let svd = rectangular.transpose().svd().unwrap();
+        assert!(svd.v.transpose().multiply(&svd.v).unwrap().approx_eq(&Matrix::identity(2), 1e-12));
+        assert!((symmetric.condition_number().unwrap() - (2.0 + 2f64.sqrt()) / (2.0 - 2f64.sqrt())).abs() < 1e-9);
+        assert_eq!(Matrix::from_rows(vec![vec![1.0, 2.0], vec![2.0, 4.0]]).unwrap().condition_number(), Ok(f64::INFINITY));
+        assert_eq!(Matrix::<f64>::identity(3).condition_number(), Ok(1.0));
+    }
+
+    #[test]
+    fn test_linear_solvers() {
+        let a = Matrix::from_rows(vec![vec![2.0, 1.0, 1.0], vec![4.0, -6.0, 0.0], vec![-2.0, 7.0, 2.0]]).unwrap();
+
+        // Test square solves, least squares and factorization reuse
+        let report = a.solve(&[5.0, -2.0, 9.0]).unwrap();
+        assert!(report.x.iter().zip(&[1.0, 1.0, 2.0]).all(|(x, expected)| (x - expected).abs() < 1e-12));
+        assert!(report.residual_norm < 1e-12);
+        let lu = a.lu().unwrap();
+        assert!(lu.solve(&[2.0, 4.0, -2.0]).unwrap().iter().zip(&[1.0, 0.0, 0.0]).all(|(x, e)| (x - e).abs() < 1e-12));
+        let columns = Matrix::from_rows(vec![vec![5.0, 2.0], vec![-2.0, 4.0], vec![9.0, -2.0]]).unwrap();
+        let solutions = a.solve_many(&columns).unwrap();
+        assert!(a.multiply(&solutions).unwrap().approx_eq(&columns, 1e-12));
+        assert_eq!(Matrix::from_rows(vec![vec![1.0, 2.0], vec![2.0, 4.0]]).unwrap().solve(&[1.0, 2.0]), Err(MatrixError::Singular));
+        assert!(matches!(a.solve(&[1.0]), Err(MatrixError::DimensionMismatch { op: "solve", .. })));
+
+        // Fit y = c + mx to (0, 1), (1, 3), (2, 4), (3, 8)
+        let design = Matrix::from_rows(vec![vec![1.0, 0.0], vec![1.0, 1.0], vec![1.0, 2.0], vec![1.0, 3.0]]).unwrap();
+        let fit = design.least_squares(&[1.0, 3.0, 4.0, 8.0]).unwrap();
+        assert!((fit.x[0] - 0.7).abs() < 1e-12 && (fit.x[1] - 2.2).abs() < 1e-12);
+        assert!((fit.residual_norm - 1.8f64.sqrt()).abs() < 1e-12);
+        let exact_fit = design.least_squares(&[1.0, 3.0, 5.0, 7.0]).unwrap();
+        assert!(exact_fit.residual_norm < 1e-12);
+        let collinear = Matrix::from_rows(vec![vec![1.0, 2.0], vec![2.0, 4.0], vec![3.0, 6.0]]).unwrap();
+        assert_eq!(collinear.least_squares(&[1.0, 2.0, 3.0]), Err(MatrixError::Singular));
     }
 }
 
 pub mod vector {
     use std::io;
+    use crate::matrix::{Matrix, MatrixError};
     use std::error::Error;
     use std::fs::File;
     use std::io::BufRead;
     use std::path::Path;
     use std::str::FromStr;
 
-    #[derive(Debug, PartialEq, Clone)]
+    #[derive(Debug, PartialEq, PartialOrd, Clone)]
     pub struct Vector {
         pub x: f64,
         pub y: f64,
@@ -288,7 +5796,7 @@ pub mod vector {
 
     pub fn read_csv_data(filename: &str) -> Result<Vec<Vector>, Box<dyn Error>> {
         let path = Path::new(filename);
-        let file = File::open(&path)?;
+        let file = File::open(path)?;
 
         let mut vectors = Vec::new();
 
@@ -307,6 +5815,52 @@ pub mod vector {
         Ok(vectors)
     }
 
+    // Principal axes of a point cloud, in order of decreasing variance
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Pca {
+        pub mean: Vector,
+        // Unit directions, one per axis
+        pub components: Vec<Vector>,
+        // Sample variance of the points along each component
+        pub variances: Vec<f64>,
+    }
+
+    impl Pca {
+        // Share of the total variance captured by each component
+        pub fn explained_variance_ratio(&self) -> Vec<f64> {
+            let total: f64 = self.variances.iter().sum();
+            self.variances.iter().map(|variance| if total > 0.0 { variance / total } else { 0.0 }).collect()
+        }
+    }
+
+    // Eigen-decomposition of the sample covariance matrix of the points
+    pub fn principal_components(points: &[Vector]) -> Result<Pca, MatrixError> {
+        if points.is_empty() {
+            return Err(MatrixError::Empty);
+        }
+
+        let count = points.len() as f64;
+        let mean = points.iter().fold(Vector::new(0.0, 0.0, 0.0), |sum, point| sum.add(point)).scalar_multiply(1.0 / count);
+        let centered: Vec<f64> = points
+            .iter()
+            .flat_map(|point| {
+                let offset = point.subtract(&mean);
+                [offset.x, offset.y, offset.z]
+            })
+            .collect();
+        let centered = Matrix::from_vec(points.len(), 3, centered)?;
+        let divisor = (count - 1.0).max(1.0);
+        let covariance = centered.transpose().multiply(&centered)?.scalar_multiply(1.0 / divisor);
+
+        let eigen = covariance.symmetric_eigen()?;
+        let components = (0..3)
+            .map(|j| Vector::new(eigen.vectors[(0, j)], eigen.vectors[(1, j)], eigen.vectors[(2, j)]))
+            .collect();
+        // Round-off can leave the variance of a flat direction slightly negative
+        let variances = eigen.values.iter().map(|value| value.max(0.0)).collect();
+        Ok(Pca { mean, components, variances })
+    }
+
     #[test]
     fn test_vector_operations() {
         let vector1 = Vector::new(2.0, 3.0, 4.0);
@@ -333,6 +5887,18 @@ pub mod vector {
 
         let magnitude_result2 = vector2.magnitude();
         assert_eq!(magnitude_result2, 10.488088481701515);
+
+        // Test principal components of points spread along one line
+        let points: Vec<Vector> = (0..5).map(|i| Vector::new(i as f64, 2.0 * i as f64, 1.0)).collect();
+        let pca = principal_components(&points).unwrap();
+        assert_eq!(pca.mean, Vector::new(2.0, 4.0, 1.0));
+        assert!((pca.variances[0] - 12.5).abs() < 1e-9);
+        assert!(pca.variances[1..].iter().all(|variance| variance.abs() < 1e-9));
+        let axis = &pca.components[0];
+        assert!((axis.magnitude() - 1.0).abs() < 1e-12);
+        assert!((axis.dot_product(&Vector::new(1.0, 2.0, 0.0)).abs() - 5f64.sqrt()).abs() < 1e-9);
+        assert!((pca.explained_variance_ratio()[0] - 1.0).abs() < 1e-9);
+        assert!(principal_components(&[]).is_err());
     }
 }
 
@@ -413,13 +5979,13 @@ pub mod logic {
         assert!(gate.is_ok());
 
         let gate = gate.unwrap();
-        assert_eq!(gate.and(), false);
-        assert_eq!(gate.or(), true);
+        assert!(!gate.and());
+        assert!(gate.or());
         assert_eq!(gate.not(), vec![false, true, false, false, true]);
-        assert_eq!(gate.nand(), true);
-        assert_eq!(gate.nor(), false);
-        assert_eq!(gate.xor(), true);
-        assert_eq!(gate.xnor(), false);
+        assert!(gate.nand());
+        assert!(!gate.nor());
+        assert!(gate.xor());
+        assert!(!gate.xnor());
 
         // Test case with empty inputs
         let empty_inputs = vec![];
@@ -431,9 +5997,11 @@ pub mod logic {
 pub mod complex {
     use csv::ReaderBuilder;
     use std::error::Error;
+    use std::fmt;
+    use std::str::FromStr;
 
     #[warn(non_snake_case)]
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, PartialEq)]
     pub struct Complex {
         pub real: f32,
         pub imaginary: f32,
@@ -471,6 +6039,58 @@ pub mod complex {
             Ok(Complex::new(real_part, imaginary_part))
         }
     }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ParseComplexError {
+        pub input: String,
+    }
+
+    impl fmt::Display for ParseComplexError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "invalid complex number '{}', expected a form like 3, 2i or 3-2i", self.input)
+        }
+    }
+
+    impl Error for ParseComplexError {}
+
+    // Parses `a`, `bi`, `a+bi` and `a-bi` (spaces allowed, `i` alone means 1i)
+    impl FromStr for Complex {
+        type Err = ParseComplexError;
+
+        fn from_str(s: &str) -> Result<Complex, ParseComplexError> {
+            let error = || ParseComplexError { input: s.to_string() };
+            let text: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+
+            let imaginary_part = match text.strip_suffix('i') {
+                None => return text.parse().map(|real| Complex::new(real, 0.0)).map_err(|_| error()),
+                Some(rest) => rest,
+            };
+
+            // The real part ends at the last sign that is not leading or part of an exponent
+            let split = imaginary_part
+                .char_indices()
+                .filter(|&(index, c)| {
+                    (c == '+' || c == '-') && index > 0 && !imaginary_part[..index].ends_with(['e', 'E'])
+                })
+                .map(|(index, _)| index)
+                .next_back();
+            let (real, imaginary) = match split {
+                Some(index) => (&imaginary_part[..index], &imaginary_part[index..]),
+                None => ("0", imaginary_part),
+            };
+
+            let imaginary = match imaginary {
+                "" | "+" => "1",
+                "-" => "-1",
+                other => other,
+            };
+
+            match (real.parse(), imaginary.parse()) {
+                (Ok(real), Ok(imaginary)) => Ok(Complex::new(real, imaginary)),
+                _ => Err(error()),
+            }
+        }
+    }
     pub fn read_complex_numbers_from_csv(filename: &str) -> Result<Vec<Complex>, Box<dyn Error>> {
         let mut reader = ReaderBuilder::new().has_headers(true).from_path(filename)?;
     
@@ -511,5 +6131,290 @@ pub mod complex {
         let division_result = complex1.divide(&complex2);
         assert_eq!(division_result.clone().unwrap().real, 0.44, "{epsilon}", epsilon = 0.01);
         assert_eq!(division_result.clone().unwrap().imaginary, 0.08, "{epsilon}", epsilon = 0.01);
+
+        // Test parsing
+        assert_eq!("3-2i".parse(), Ok(Complex::new(3.0, -2.0)));
+        assert_eq!(" -1.5 + i ".parse(), Ok(Complex::new(-1.5, 1.0)));
+        assert_eq!("-2i".parse(), Ok(Complex::new(0.0, -2.0)));
+        assert_eq!("4".parse(), Ok(Complex::new(4.0, 0.0)));
+        assert_eq!("1e-3+2e+1i".parse(), Ok(Complex::new(0.001, 20.0)));
+        assert!("3+xi".parse::<Complex>().is_err());
+    }
+}
+
+pub mod rational {
+    use std::cmp::Ordering;
+    use std::error::Error;
+    use std::fmt;
+    use std::str::FromStr;
+
+    // Exact fraction kept in lowest terms with a positive denominator, so
+    // derived equality and hashing agree with numeric equality. Arithmetic is
+    // carried out in i128 and panics if a reduced result leaves the i64 range,
+    // like ordinary integer overflow; the `checked_` methods return `None` instead.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Rational {
+        numerator: i64,
+        denominator: i64,
+    }
+
+    fn gcd(mut a: i128, mut b: i128) -> i128 {
+        while b != 0 {
+            let remainder = a % b;
+            a = b;
+            b = remainder;
+        }
+        a.abs()
+    }
+
+    impl Rational {
+        // Panics if `denominator` is zero
+        pub fn new(numerator: i64, denominator: i64) -> Rational {
+            assert!(denominator != 0, "rational denominator must not be zero");
+            Rational::reduce(numerator as i128, denominator as i128)
+        }
+
+        pub fn from_integer(value: i64) -> Rational {
+            Rational { numerator: value, denominator: 1 }
+        }
+
+        pub fn zero() -> Rational {
+            Rational::from_integer(0)
+        }
+
+        pub fn one() -> Rational {
+            Rational::from_integer(1)
+        }
+
+        fn reduce(numerator: i128, denominator: i128) -> Rational {
+            Rational::try_reduce(Some(numerator), Some(denominator)).expect("rational overflow")
+        }
+
+        // Lowest terms, or `None` if either part is missing or does not fit in i64
+        fn try_reduce(numerator: Option<i128>, denominator: Option<i128>) -> Option<Rational> {
+            let (numerator, denominator) = (numerator?, denominator?);
+            let divisor = gcd(numerator, denominator).max(1);
+            let sign = if denominator < 0 { -1 } else { 1 };
+            Some(Rational {
+                numerator: i64::try_from(sign * (numerator / divisor)).ok()?,
+                denominator: i64::try_from(sign * (denominator / divisor)).ok()?,
+            })
+        }
+
+        pub fn numerator(&self) -> i64 {
+            self.numerator
+        }
+
+        pub fn denominator(&self) -> i64 {
+            self.denominator
+        }
+
+        pub fn is_zero(&self) -> bool {
+            self.numerator == 0
+        }
+
+        pub fn is_integer(&self) -> bool {
+            self.denominator == 1
+        }
+
+        pub fn add(&self, other: &Rational) -> Rational {
+            self.checked_add(other).expect("rational overflow")
+        }
+
+        pub fn subtract(&self, other: &Rational) -> Rational {
+            self.checked_subtract(other).expect("rational overflow")
+        }
+
+        pub fn multiply(&self, other: &Rational) -> Rational {
+            let (a, b, c, d) = self.widen(other);
+            Rational::reduce(a * c, b * d)
+        }
+
+        pub fn divide(&self, other: &Rational) -> Result<Rational, &'static str> {
+            if other.is_zero() {
+                return Err("Division by zero is not allowed.");
+            }
+
+            let (a, b, c, d) = self.widen(other);
+            Ok(Rational::reduce(a * d, b * c))
+        }
+
+        pub fn checked_add(&self, other: &Rational) -> Option<Rational> {
+            let (a, b, c, d) = self.widen(other);
+            Rational::try_reduce((a * d).checked_add(c * b), Some(b * d))
+        }
+
+        pub fn checked_subtract(&self, other: &Rational) -> Option<Rational> {
+            let (a, b, c, d) = self.widen(other);
+            Rational::try_reduce((a * d).checked_sub(c * b), Some(b * d))
+        }
+
+        pub fn checked_multiply(&self, other: &Rational) -> Option<Rational> {
+            let (a, b, c, d) = self.widen(other);
+            Rational::try_reduce(Some(a * c), Some(b * d))
+        }
+
+        // `None` on division by zero as well as on overflow
+        pub fn checked_divide(&self, other: &Rational) -> Option<Rational> {
+            if other.is_zero() {
+                return None;
+            }
+
+            let (a, b, c, d) = self.widen(other);
+            Rational::try_reduce(Some(a * d), Some(b * c))
+        }
+
+        pub fn checked_negate(&self) -> Option<Rational> {
+            Rational::try_reduce(Some(-(self.numerator as i128)), Some(self.denominator as i128))
+        }
+
+        pub fn negate(&self) -> Rational {
+            Rational::reduce(-(self.numerator as i128), self.denominator as i128)
+        }
+
+        pub fn reciprocal(&self) -> Option<Rational> {
+            Rational::one().divide(self).ok()
+        }
+
+        pub fn abs(&self) -> Rational {
+            if self.numerator < 0 { self.negate() } else { *self }
+        }
+
+        pub fn to_f64(&self) -> f64 {
+            self.numerator as f64 / self.denominator as f64
+        }
+
+        fn widen(&self, other: &Rational) -> (i128, i128, i128, i128) {
+            (
+                self.numerator as i128,
+                self.denominator as i128,
+                other.numerator as i128,
+                other.denominator as i128,
+            )
+        }
+    }
+
+    impl From<i64> for Rational {
+        fn from(value: i64) -> Rational {
+            Rational::from_integer(value)
+        }
+    }
+
+    impl From<i32> for Rational {
+        fn from(value: i32) -> Rational {
+            Rational::from_integer(value as i64)
+        }
+    }
+
+    impl Ord for Rational {
+        fn cmp(&self, other: &Rational) -> Ordering {
+            let (a, b, c, d) = self.widen(other);
+            (a * d).cmp(&(c * b))
+        }
+    }
+
+    impl PartialOrd for Rational {
+        fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl fmt::Display for Rational {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if self.is_integer() {
+                write!(f, "{}", self.numerator)
+            } else {
+                write!(f, "{}/{}", self.numerator, self.denominator)
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ParseRationalError {
+        pub input: String,
+    }
+
+    impl fmt::Display for ParseRationalError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "invalid rational number '{}', expected a form like 3, -2/5 or 1.25", self.input)
+        }
+    }
+
+    impl Error for ParseRationalError {}
+
+    // Parses integers, fractions `a/b` and terminating decimals such as `1.25`
+    impl FromStr for Rational {
+        type Err = ParseRationalError;
+
+        fn from_str(s: &str) -> Result<Rational, ParseRationalError> {
+            let error = || ParseRationalError { input: s.to_string() };
+            let text = s.trim();
+
+            if let Some((numerator, denominator)) = text.split_once('/') {
+                let numerator: i64 = numerator.trim().parse().map_err(|_| error())?;
+                let denominator: i64 = denominator.trim().parse().map_err(|_| error())?;
+                if denominator == 0 {
+                    return Err(error());
+                }
+                return Ok(Rational::new(numerator, denominator));
+            }
+
+            match text.split_once('.') {
+                None => text.parse().map(Rational::from_integer).map_err(|_| error()),
+                Some((whole, fraction)) => {
+                    if fraction.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+                        return Err(error());
+                    }
+                    let scale = u32::try_from(fraction.len()).ok().and_then(|len| 10i64.checked_pow(len)).ok_or_else(error)?;
+                    let digits: i64 = format!("{}{}", whole, fraction).parse().map_err(|_| error())?;
+                    Ok(Rational::new(digits, scale))
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rational_operations() {
+        let half = Rational::new(1, 2);
+        let third = Rational::new(-2, -6);
+
+        // Test normalization
+        assert_eq!(third, Rational::new(1, 3));
+        assert_eq!(Rational::new(4, -6), Rational::new(-2, 3));
+        assert_eq!(Rational::new(0, -5), Rational::zero());
+        assert_eq!(Rational::new(-4, -6).denominator(), 3);
+
+        // Test arithmetic
+        assert_eq!(half.add(&third), Rational::new(5, 6));
+        assert_eq!(half.subtract(&third), Rational::new(1, 6));
+        assert_eq!(half.multiply(&third), Rational::new(1, 6));
+        assert_eq!(half.divide(&third), Ok(Rational::new(3, 2)));
+        assert!(half.divide(&Rational::zero()).is_err());
+        assert_eq!(Rational::new(-3, 4).reciprocal(), Some(Rational::new(-4, 3)));
+        assert_eq!(Rational::zero().reciprocal(), None);
+        assert!(Rational::new(-1, 2) < third && third < half);
+
+        // Test checked arithmetic
+        let max = Rational::from_integer(i64::MAX);
+        assert_eq!(half.checked_add(&third), Some(Rational::new(5, 6)));
+        assert_eq!(half.checked_divide(&third), Some(Rational::new(3, 2)));
+        assert_eq!(max.checked_add(&Rational::one()), None);
+        assert_eq!(max.checked_subtract(&Rational::new(1, i64::MAX)), None);
+        assert_eq!(max.checked_multiply(&Rational::new(2, 3)), None);
+        assert_eq!(half.checked_divide(&Rational::zero()), None);
+        assert_eq!(Rational::from_integer(i64::MIN).checked_negate(), None);
+        assert_eq!(max.checked_negate(), Some(Rational::from_integer(-i64::MAX)));
+
+        // Test formatting and parsing
+        assert_eq!(Rational::new(6, -4).to_string(), "-3/2");
+        assert_eq!(Rational::from(7).to_string(), "7");
+        assert_eq!("-2/5".parse(), Ok(Rational::new(-2, 5)));
+        assert_eq!(" 3 / 9 ".parse(), Ok(Rational::new(1, 3)));
+        assert_eq!("1.25".parse(), Ok(Rational::new(5, 4)));
+        assert_eq!("-0.5".parse(), Ok(Rational::new(-1, 2)));
+        assert_eq!("12".parse(), Ok(Rational::from_integer(12)));
+        assert!("1/0".parse::<Rational>().is_err());
+        assert!("x/2".parse::<Rational>().is_err());
+        assert!("1.".parse::<Rational>().is_err());
     }
 }
@@ -1,9 +1,13 @@
 use std::io;
 use project::math_set::*;
+use project::set_expr;
+use project::partition::is_partition;
+use project::venn;
 use project::matrix::*;
 use project::vector::*;
 use project::logic::*;
 use project::complex::*;
+use project::rational::Rational;
 
 fn main() {
     println!("Which Math Object do you want to work on? ");
@@ -35,7 +39,7 @@ fn main() {
                 return;
             }
         
-            if let Ok(sets) = read_sets_from_csv("sets.csv") {
+            if let Ok(sets) = read_sets_from_csv::<i32>("sets.csv") {
                 if sets.len() < num_sets {
                     eprintln!("Not enough sets in the file to perform operations.");
                     return;
@@ -50,9 +54,9 @@ fn main() {
                 let mut union_result = sets_to_use[0].clone();
                 let mut intersection_result = sets_to_use[0].clone();
                 let mut difference_result = sets_to_use[0].clone();
+                let mut symmetric_difference_result = sets_to_use[0].clone();
             
-                for set_index in 1..num_sets {
-                    let current_set = &sets_to_use[set_index];
+                for current_set in &sets_to_use[1..] {
             
                     // Union
                     union_result = union_result.union(current_set);
@@ -62,11 +66,60 @@ fn main() {
             
                     // Difference
                     difference_result = difference_result.difference(current_set);
+
+                    // Symmetric difference
+                    symmetric_difference_result = symmetric_difference_result.symmetric_difference(current_set);
                 }
         
                 println!("Union: {:?}", union_result);
                 println!("Intersection: {:?}", intersection_result);
                 println!("Difference: {:?}", difference_result);
+                println!("Symmetric Difference: {:?}", symmetric_difference_result);
+
+                // Complements are taken relative to the union of the chosen sets
+                for (index, set) in sets_to_use.iter().enumerate() {
+                    println!("Complement of Set {} (size {}): {:?}", index + 1, set.cardinality(), set.complement(&union_result));
+                }
+
+                for (i, first) in sets_to_use.iter().enumerate() {
+                    for (j, second) in sets_to_use.iter().enumerate().skip(i + 1) {
+                        println!("Set {} vs Set {}:", i + 1, j + 1);
+                        println!("  subset: {}, proper subset: {}, superset: {}, disjoint: {}",
+                            first.is_subset(second),
+                            first.is_proper_subset(second),
+                            first.is_superset(second),
+                            first.is_disjoint(second));
+                    }
+                }
+
+                println!("Chosen sets form a partition of their union: {}", if is_partition(sets_to_use) { "yes" } else { "no" });
+
+                if (2..=4).contains(&num_sets) {
+                    if let Ok(table) = venn::region_table(sets_to_use) {
+                        println!("Venn regions:");
+                        print!("{}", table);
+                    }
+                    println!("Membership table:");
+                    print!("{}", venn::membership_table_markdown(sets_to_use));
+
+                    match venn::write_venn_svg(sets_to_use, "venn.svg") {
+                        Ok(()) => println!("Venn diagram written to venn.svg"),
+                        Err(e) => eprintln!("Error writing Venn diagram: {}", e),
+                    }
+                }
+
+                // Free-form expressions may name any row of the file, A being the first
+                let universe = sets.iter().fold(MathSet::new(Vec::new()), |acc, set| acc.union(set));
+                let mut expression = String::new();
+                println!("Enter a set expression over A, B, C... such as (A ∪ B) \\ (C ∩ D), or leave blank to skip:");
+                io::stdin().read_line(&mut expression).expect("Failed to read line");
+
+                if !expression.trim().is_empty() {
+                    match set_expr::evaluate(expression.trim(), &sets, &universe) {
+                        Ok(result) => println!("{} = {:?}", expression.trim(), result),
+                        Err(e) => eprintln!("Invalid set expression: {}", e),
+                    }
+                }
         
             } else {
                 eprintln!("Error reading sets from CSV file.");
@@ -75,13 +128,47 @@ fn main() {
         }
 
         "2" => {
-            if let Ok(matrices) = read_matrices_from_csv("matrix.csv") {
+            if let Ok(matrices) = read_matrices_from_csv::<i32>("matrix.csv") {
                 if matrices.len() < 2 {
                     eprintln!("Not enough matrices in the file to perform operations.");
                     return;
                 }
         
-                let matrix_vec: Vec<Matrix> = matrices.iter().map(|m| m.clone()).collect();
+                let matrix_vec: Vec<Matrix<i32>> = matrices.to_vec();
+
+                // Report the properties of every matrix in the file
+                for (index, matrix) in matrix_vec.iter().enumerate() {
+                    println!("Matrix {} ({}×{}):", index + 1, matrix.rows(), matrix.cols());
+                    print_matrix(matrix);
+                    println!("Transpose:");
+                    print_matrix(&matrix.transpose());
+                    match matrix.trace() {
+                        Ok(trace) => println!("Trace: {}", trace),
+                        Err(error) => println!("Trace: {}", error),
+                    }
+                    // Widen to i64 so the elimination cannot overflow on i32 input
+                    match matrix.widen().determinant() {
+                        Ok(determinant) => println!("Determinant: {}", determinant),
+                        Err(error) => println!("Determinant: {}", error),
+                    }
+                    match matrix.widen().rank() {
+                        Ok(rank) => println!("Rank: {}", rank),
+                        Err(error) => println!("Rank: {}", error),
+                    }
+                    let real = matrix.map(|&x| x as f64);
+                    match real.condition_number() {
+                        Ok(condition) => println!("Condition number: {:.4e}", condition),
+                        Err(error) => println!("Condition number: {}", error),
+                    }
+                    match real.eigenvalues() {
+                        Ok(values) => {
+                            let values: Vec<String> = values.iter().map(|z| format!("{:.4}{:+.4}i", z.0, z.1)).collect();
+                            println!("Eigenvalues: {}", values.join(", "));
+                        }
+                        Err(error) => println!("Eigenvalues: {}", error),
+                    }
+                    println!();
+                }
         
                 let mut num_sets = String::new();
                 println!("How many sets of matrices do you want to operate on?");
@@ -119,11 +206,11 @@ fn main() {
                     print_matrix(matrix);
                 }
         
-                let mut result_addition = Matrix::new(matrices[0].data.len(), matrices[0].data[0].len());
+                let mut result_addition = Matrix::new(matrices[0].rows(), matrices[0].cols());
         
-                let mut result_subtraction = Matrix::new(matrices[0].data.len(), matrices[0].data[0].len());
+                let mut result_subtraction = Matrix::new(matrices[0].rows(), matrices[0].cols());
         
-                let mut result_multiplication = Matrix::new(matrices[0].data.len(), matrices[0].data[0].len());
+                let mut result_multiplication = Matrix::new(matrices[0].rows(), matrices[0].cols());
         
                 for set in 0..num_sets {
                     let matrix1 = &matrix_vec[set];
@@ -155,6 +242,108 @@ fn main() {
         
                 println!("Result of Multiplication:");
                 print_matrix(&result_multiplication);
+
+                // Commands on one matrix of the file, e.g. "explain 2 latex" or "solve 1"
+                let mut command = String::new();
+                println!("Enter \"explain <matrix number> [text|markdown|latex]\" to show a row reduction step by step,");
+                println!("\"solve <matrix number>\" to solve a matrix as an augmented system [A | b], or leave blank to skip:");
+                io::stdin().read_line(&mut command).expect("Failed to read line");
+
+                let words: Vec<&str> = command.split_whitespace().collect();
+                if let [action @ ("explain" | "solve"), number, rest @ ..] = words.as_slice() {
+                    let matrix = match number.parse::<usize>() {
+                        Ok(n) if (1..=matrix_vec.len()).contains(&n) => &matrix_vec[n - 1],
+                        _ => {
+                            eprintln!("Invalid matrix number. Choose one from 1 to {}.", matrix_vec.len());
+                            return;
+                        }
+                    };
+
+                    if *action == "explain" {
+                        let reduction = match matrix.map(|&x| Rational::from(x)).explain_rref() {
+                            Ok(reduction) => reduction,
+                            Err(e) => {
+                                eprintln!("Cannot explain the row reduction: {}", e);
+                                return;
+                            }
+                        };
+                        match rest.first().copied().unwrap_or("text") {
+                            "text" => print!("{}", reduction.to_text()),
+                            "markdown" => print!("{}", reduction.to_markdown()),
+                            "latex" => print!("{}", reduction.to_latex()),
+                            other => eprintln!("Unknown format '{}'. Use text, markdown or latex.", other),
+                        }
+                        return;
+                    }
+
+                    if matrix.cols() < 2 {
+                        eprintln!("An augmented matrix needs at least one coefficient column and a right-hand side column.");
+                        return;
+                    }
+                    let unknowns = matrix.cols() - 1;
+                    let coefficients = matrix.select_columns(&(0..unknowns).collect::<Vec<_>>());
+                    let rhs = matrix.column(unknowns);
+                    let real = coefficients.map(|&x| x as f64);
+                    let real_rhs: Vec<f64> = rhs.iter().map(|&x| x as f64).collect();
+                    let exact_rhs: Vec<Rational> = rhs.iter().map(|&x| Rational::from(x)).collect();
+
+                    // Exact elimination classifies the system; the float solvers add diagnostics
+                    match coefficients.map(|&x| Rational::from(x)).solve(&exact_rhs) {
+                        Ok(Solution::Unique(x)) => {
+                            println!("Unique solution:");
+                            for (index, value) in x.iter().enumerate() {
+                                println!("x{} = {}", index + 1, value);
+                            }
+                            let report = if real.is_square() { real.solve(&real_rhs) } else { real.least_squares(&real_rhs) };
+                            if let Ok(report) = report {
+                                println!("Residual norm: {:.4e}", report.residual_norm);
+                            }
+                            if let Ok(condition) = real.condition_number() {
+                                println!("Condition number: {:.4e}", condition);
+                            }
+                        }
+                        Ok(Solution::Infinite { particular, null_space }) => {
+                            println!(
+                                "Infinitely many solutions: the coefficients have rank {} but there are {} unknowns.",
+                                unknowns - null_space.len(),
+                                unknowns
+                            );
+                            let format = |values: &[Rational]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
+                            let mut general = format!("x = ({})", format(&particular));
+                            for (index, direction) in null_space.iter().enumerate() {
+                                general += &format!(" + t{}·({})", index + 1, format(direction));
+                            }
+                            println!("{}", general);
+                        }
+                        Err(MatrixError::Inconsistent) => {
+                            println!("No solution: row reduction of [A | b] produces a row reading 0 = 1, so the equations contradict each other.");
+                            if let Ok(fit) = real.least_squares(&real_rhs) {
+                                let values: Vec<String> = fit.x.iter().map(|v| format!("{:.6}", v)).collect();
+                                println!("Least-squares fit: x = ({}), residual norm {:.4e}", values.join(", "), fit.residual_norm);
+                            }
+                        }
+                        Err(MatrixError::Overflow { .. }) => {
+                            // Exact fractions outgrew i64; floating point can still give an answer
+                            println!("Exact elimination overflowed, falling back to floating point.");
+                            let report = if real.is_square() { real.solve(&real_rhs) } else { real.least_squares(&real_rhs) };
+                            match report {
+                                Ok(report) => {
+                                    for (index, value) in report.x.iter().enumerate() {
+                                        println!("x{} ≈ {:.6}", index + 1, value);
+                                    }
+                                    println!("Residual norm: {:.4e}", report.residual_norm);
+                                    if let Ok(condition) = real.condition_number() {
+                                        println!("Condition number: {:.4e}", condition);
+                                    }
+                                }
+                                Err(e) => eprintln!("Cannot solve the system in floating point either: {}", e),
+                            }
+                        }
+                        Err(e) => eprintln!("Cannot solve the system: {}", e),
+                    }
+                } else if !words.is_empty() {
+                    eprintln!("Unrecognized command. Use explain <matrix number> [text|markdown|latex] or solve <matrix number>.");
+                }
         
             } else {
                 eprintln!("Error reading matrices from CSV file.");
@@ -234,6 +423,17 @@ fn main() {
                     println!("Scalar multiplication of Vector {}: {:?}", index + 1 , scalar_multiply);
                 }
 
+                // Principal axes of the selected vectors taken as a point cloud
+                match principal_components(&vectors[..num_vectors]) {
+                    Ok(pca) => {
+                        println!("Mean point: {:?}", pca.mean);
+                        for ((component, variance), ratio) in pca.components.iter().zip(&pca.variances).zip(pca.explained_variance_ratio()) {
+                            println!("Principal axis {:?}: variance {:.4} ({:.1}%)", component, variance, ratio * 100.0);
+                        }
+                    }
+                    Err(e) => eprintln!("Error computing principal components: {}", e),
+                }
+
             } else {
                 eprintln!("Error reading vectors from CSV file.");
             }
@@ -316,8 +516,7 @@ fn main() {
                 let mut multiplication_result = numbers_to_use[0].clone();
                 let mut division_result = numbers_to_use[0].clone();
 
-                for num_index in 1..num_complex_numbers {
-                    let current_number = &numbers_to_use[num_index];
+                for current_number in &numbers_to_use[1..] {
 
                     // Addition
                     addition_result = addition_result.add(current_number);